@@ -0,0 +1,145 @@
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, Ident, Type};
+
+//
+// `#[derive(PileKeys)]`, for the key enums of `libmtg::keys`.
+//
+// The keys of an enum are its variants in order: a unit variant is one key,
+// a variant holding another key enum, like `Dual::A(Allied)`, is all the keys
+// of that enum. For
+//
+//     #[derive(PileKeys)]
+//     #[land]
+//     pub enum Dual { A(Allied), E(Enemy) }
+//
+// we get `impl PileKey for Dual`, with `Dual::A(x)` at `x.to_uint()` and
+// `Dual::E(x)` at `5 + x.to_uint()`, and a unit struct `DualKeys` for
+// `GenPile<DualKeys>`. The keys of a variant are lands if `#[land]` is on
+// the variant or the enum, or if they are lands of the enum it holds.
+//
+
+#[proc_macro_derive(PileKeys, attributes(land))]
+pub fn derive_pile_keys(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match pile_keys(&input) {
+        Ok(ts) => ts.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn is_land(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|a| a.path().is_ident("land"))
+}
+
+fn pile_keys(input: &DeriveInput) -> Result<TokenStream2, Error> {
+    let name = &input.ident;
+    let vis = &input.vis;
+    let data = match input.data {
+        Data::Enum(ref data) => data,
+        _ => return Err(Error::new_spanned(name, "PileKeys can only be derived for enums")),
+    };
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(&input.generics, "PileKeys can't be derived for generic enums"));
+    }
+    let all_land = is_land(&input.attrs);
+
+    // For each variant: its size, how to get its offset back, and whether
+    // its keys are lands.
+    let mut sizes = vec![];
+    let mut to_uint = vec![];
+    let mut from_uint = vec![];
+    let mut lands = vec![];
+
+    for v in &data.variants {
+        let var = &v.ident;
+        let land = all_land || is_land(&v.attrs);
+        let offset = quote! { 0 #(+ #sizes)* };
+        match v.fields {
+            Fields::Unit => {
+                sizes.push(quote! { 1 });
+                to_uint.push(quote! { #name::#var => #offset });
+                from_uint.push(quote! {
+                    if n < 1 { return #name::#var; }
+                    n -= 1;
+                });
+                lands.push(quote! {
+                    if n < 1 { return #land; }
+                    n -= 1;
+                });
+            }
+            Fields::Unnamed(ref f) if f.unnamed.len() == 1 => {
+                let ty: &Type = &f.unnamed[0].ty;
+                let size = quote! { <#ty as ::libmtg::keys::PileKey>::SIZE };
+                to_uint.push(quote! { #name::#var(x) => #offset + ::libmtg::keys::PileKey::to_uint(x) });
+                from_uint.push(quote! {
+                    if n < #size { return #name::#var(<#ty as ::libmtg::keys::PileKey>::from_uint(n)); }
+                    n -= #size;
+                });
+                let inner = if land { quote! { true } } else { quote! { <#ty as ::libmtg::keys::PileKey>::is_land(n) } };
+                lands.push(quote! {
+                    if n < #size { return #inner; }
+                    n -= #size;
+                });
+                sizes.push(size);
+            }
+            _ => return Err(Error::new_spanned(v, "PileKeys variants must be units or hold a single key")),
+        }
+    }
+
+    let keys = Ident::new(&format!("{}Keys", name), Span::call_site());
+    let out_of_range = format!("{} key {{}} out of range", name);
+
+    Ok(quote! {
+        impl ::libmtg::keys::PileKey for #name {
+            const SIZE: usize = 0 #(+ #sizes)*;
+
+            fn to_uint(self) -> usize {
+                match self {
+                    #(#to_uint,)*
+                }
+            }
+
+            #[allow(unused_assignments)]
+            fn from_uint(n: usize) -> #name {
+                let k = n;
+                let mut n = n;
+                #(#from_uint)*
+                panic!(#out_of_range, k)
+            }
+
+            #[allow(unused_assignments)]
+            fn is_land(n: usize) -> bool {
+                let mut n = n;
+                #(#lands)*
+                false
+            }
+        }
+
+        #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+        #vis struct #keys;
+
+        impl ::libmtg::keys::Keys for #keys {
+            type Key = #name;
+
+            fn size(&self) -> usize {
+                <#name as ::libmtg::keys::PileKey>::SIZE
+            }
+
+            fn to_uint(&self, k: #name) -> usize {
+                ::libmtg::keys::PileKey::to_uint(k)
+            }
+
+            fn from_uint(&self, n: usize) -> #name {
+                <#name as ::libmtg::keys::PileKey>::from_uint(n)
+            }
+
+            fn is_land(&self, n: usize) -> bool {
+                <#name as ::libmtg::keys::PileKey>::is_land(n)
+            }
+        }
+    })
+}
@@ -0,0 +1,243 @@
+use crate::colors::Color;
+use crate::logic::battlefield::{Cantrip, Effect, Effects, LandEffect, LookPolicy};
+use crate::mtgjson::MtgError;
+use crate::pile::{mask, GenPile, GenPileKeys};
+use std::ops::Index;
+
+//
+// Piles over named categories of cards.
+//
+// A `GenPile` counts cards by key, and the keys are positions: which key is
+// a land, and what each key stands for, is up to the caller to track. Here
+// we register the categories instead, each with a name and what we know
+// about its cards, and get back a handle to index the piles with:
+//
+//     let mut cats = Categories::new();
+//     let forest = cats.land("Forest", &[Color::G]);
+//     let bear = cats.spell("Bear", 2, &[Color::G]);
+//     let deck = cats.pile(&[(forest, 17), (bear, 23)]);
+//     ... hand[forest] >= 2 && hand[bear] > 0 ...
+//
+// The piles are plain `GenPile`s, so they work with all the `turn0` models.
+//
+
+/// A handle to a category, to index piles with.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Cat(usize);
+
+impl Cat {
+    /// The key of the category in the piles.
+    pub fn key(self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Category {
+    pub name: String,
+    pub land: bool,
+    /// For a land, the colors it taps for; for a spell, its colors.
+    pub colors: Vec<Color>,
+    pub mana_value: usize,
+    /// For a land, whether it enters tapped.
+    pub tapped: bool,
+    /// What the card does when cast or played, e.g. `[Scry(1), Draw(1)]`
+    /// for Opt.
+    pub effects: Vec<Effect>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Categories {
+    cats: Vec<Category>,
+}
+
+impl Categories {
+    pub fn new() -> Categories {
+        Categories { cats: vec![] }
+    }
+
+    fn add(&mut self, cat: Category) -> Cat {
+        assert!(self.cat(&cat.name).is_none(), "Category '{}' added twice", cat.name);
+        assert!(self.cats.len() < 64, "At most 64 categories");
+        self.cats.push(cat);
+        Cat(self.cats.len() - 1)
+    }
+
+    /// Add a category of lands tapping for `colors`.
+    pub fn land(&mut self, name: &str, colors: &[Color]) -> Cat {
+        self.land_with(name, colors, false, &[])
+    }
+
+    /// Add a category of lands that do something when played, like the scry
+    /// lands: `land_with("Temple", &[U, B], true, &[Scry(1)])`.
+    pub fn land_with(&mut self, name: &str, colors: &[Color], tapped: bool, effects: &[Effect]) -> Cat {
+        self.add(Category {
+            name: name.to_string(),
+            land: true,
+            colors: colors.to_vec(),
+            mana_value: 0,
+            tapped,
+            effects: effects.to_vec(),
+        })
+    }
+
+    /// Add a category of spells.
+    pub fn spell(&mut self, name: &str, mana_value: usize, colors: &[Color]) -> Cat {
+        self.spell_with(name, mana_value, colors, &[])
+    }
+
+    /// Add a category of spells that draw or look at cards, like Opt, which
+    /// are cast as soon as possible. `colors` has a color for each colored
+    /// mana symbol of the cost.
+    pub fn spell_with(&mut self, name: &str, mana_value: usize, colors: &[Color], effects: &[Effect]) -> Cat {
+        self.add(Category {
+            name: name.to_string(),
+            land: false,
+            colors: colors.to_vec(),
+            mana_value,
+            tapped: false,
+            effects: effects.to_vec(),
+        })
+    }
+
+    /// Add a category of spells that draw `draws` cards.
+    pub fn cantrip(&mut self, name: &str, mana_value: usize, colors: &[Color], draws: usize) -> Cat {
+        self.spell_with(name, mana_value, colors, &[Effect::Draw(draws)])
+    }
+
+    /// The effects of the categories, for `logic::battlefield` and the
+    /// simulator. Spells are cast with as many lands as their mana value, and
+    /// a land of each color for each symbol of it.
+    pub fn effects(&self, look: Box<dyn LookPolicy<GenPile>>) -> Effects<GenPile> {
+        let cantrips = self
+            .all()
+            .filter(|&c| !self[c].land && !self[c].effects.is_empty())
+            .map(|c| {
+                let (cats, colors) = (self.clone(), self[c].colors.clone());
+                Cantrip {
+                    card: self.pile(&[(c, 1)]),
+                    cost: self[c].mana_value,
+                    effects: self[c].effects.clone(),
+                    colors: Box::new(move |lands: &GenPile| {
+                        colors.iter().all(|&col| cats.sources(lands, col) >= colors.iter().filter(|&&x| x == col).count())
+                    }),
+                }
+            })
+            .collect();
+        let lands = self
+            .all()
+            .filter(|&c| self[c].land && (self[c].tapped || !self[c].effects.is_empty()))
+            .map(|c| LandEffect { card: self.pile(&[(c, 1)]), tapped: self[c].tapped, effects: self[c].effects.clone() })
+            .collect();
+        Effects { cantrips, lands, look }
+    }
+
+    pub fn cat(&self, name: &str) -> Option<Cat> {
+        self.cats.iter().position(|c| c.name == name).map(Cat)
+    }
+
+    pub fn all(&self) -> impl Iterator<Item = Cat> {
+        (0..self.cats.len()).map(Cat)
+    }
+
+    pub fn keys(&self) -> GenPileKeys {
+        let lands = self.cats.iter().enumerate().filter(|(_, c)| c.land).fold(0, |m, (i, _)| m | 1 << i);
+        let keys = GenPileKeys::with_lands(self.cats.len(), lands);
+        self.cats.iter().enumerate().filter(|(_, c)| c.land).fold(keys, |k, (i, c)| k.with_land(i, mask(&c.colors), c.tapped))
+    }
+
+    /// The pile with these many cards of each category, and none of the
+    /// others.
+    pub fn pile(&self, counts: &[(Cat, usize)]) -> GenPile {
+        let mut e = vec![0; self.cats.len()];
+        for &(cat, n) in counts {
+            e[cat.0] += n
+        }
+        GenPile::new(e, self.keys())
+    }
+
+    /// `pile`, by category name.
+    pub fn named(&self, counts: &[(&str, usize)]) -> Result<GenPile, MtgError> {
+        let counts = counts
+            .iter()
+            .map(|&(name, n)| {
+                self.cat(name).map(|c| (c, n)).ok_or_else(|| MtgError::General(format!("No category '{}'", name)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(self.pile(&counts))
+    }
+
+    /// The number of cards in `pile` of the categories for which `pred`
+    /// holds.
+    pub fn count<F: Fn(&Category) -> bool>(&self, pile: &GenPile, pred: F) -> usize {
+        self.all().filter(|&c| pred(&self[c])).map(|c| pile[c]).sum()
+    }
+
+    /// The cards of `pile` by category, e.g. "2 Forest, 1 Temple, 4 Bear".
+    pub fn describe(&self, pile: &GenPile) -> String {
+        let cards: Vec<String> = self.all().filter(|&c| pile[c] > 0).map(|c| format!("{} {}", pile[c], self[c].name)).collect();
+        if cards.is_empty() { "no cards".to_string() } else { cards.join(", ") }
+    }
+
+    /// The number of lands in `pile` tapping for `color`.
+    pub fn sources(&self, pile: &GenPile, color: Color) -> usize {
+        self.count(pile, |c| c.land && c.colors.contains(&color))
+    }
+}
+
+impl Index<Cat> for Categories {
+    type Output = Category;
+
+    fn index(&self, cat: Cat) -> &Category {
+        &self.cats[cat.0]
+    }
+}
+
+impl Index<Cat> for GenPile {
+    type Output = usize;
+
+    #[inline]
+    fn index(&self, cat: Cat) -> &usize {
+        &self[cat.0]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pile::LandPile;
+
+    #[test]
+    fn by_name() {
+        let mut cats = Categories::new();
+        let forest = cats.land("Forest", &[Color::G]);
+        let temple = cats.land("Temple", &[Color::G, Color::U]);
+        let bear = cats.spell("Bear", 2, &[Color::G]);
+
+        let deck = cats.named(&[("Forest", 13), ("Temple", 4), ("Bear", 23)]).unwrap();
+        assert_eq!(deck, cats.pile(&[(forest, 13), (temple, 4), (bear, 23)]));
+        assert_eq!((deck.lands(), deck.spells()), (17, 23));
+        assert_eq!((deck[temple], cats.sources(&deck, Color::G), cats.sources(&deck, Color::U)), (4, 17, 4));
+        assert_eq!(cats.count(&deck, |c| c.mana_value == 2), 23);
+        assert!(cats.named(&[("Island", 1)]).is_err());
+        assert_eq!(cats.describe(&cats.pile(&[(forest, 2), (bear, 4)])), "2 Forest, 4 Bear");
+    }
+
+    #[test]
+    fn land_drop() {
+        let mut cats = Categories::new();
+        let scry = cats.land_with("Temple", &[Color::U, Color::B], true, &[Effect::Scry(1)]);
+        let island = cats.land("Island", &[Color::U]);
+        let swamp = cats.land("Swamp", &[Color::B]);
+        let drop = |hand: &[(Cat, usize)], played: &[(Cat, usize)]| cats.pile(hand).land_drop(&cats.pile(played));
+
+        // The basic before the tapped scry land, which comes first otherwise.
+        assert_eq!(drop(&[(scry, 1), (island, 1)], &[]), Some(cats.pile(&[(island, 1)])));
+        assert_eq!(drop(&[(scry, 1)], &[(island, 1)]), Some(cats.pile(&[(scry, 1)])));
+
+        // And the basic adding a color we don't have.
+        assert_eq!(drop(&[(scry, 1), (island, 1), (swamp, 1)], &[(island, 1)]), Some(cats.pile(&[(swamp, 1)])));
+        assert_eq!(drop(&[(island, 1), (swamp, 1)], &[(swamp, 2)]), Some(cats.pile(&[(island, 1)])));
+        assert_eq!(drop(&[], &[(island, 1)]), None);
+    }
+}
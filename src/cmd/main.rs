@@ -3,11 +3,19 @@ extern crate libmtg;
 extern crate unicode_segmentation;
 
 //use mtg::logic::*;
-use libmtg::logic::{dual, frank_table, investigate, show_card_text, summary_c, summary_perc};
+use libmtg::logic::{battlefield, chance, dual, explain, frank_table, investigate, optimal, play_draw, show_card_text, summary_c, summary_c_exact, summary_perc};
+use libmtg::logic::{LandWindow, Mulligan, Opening, PlayDraw, Rules};
 
+use libmtg::deck::Deck;
+use libmtg::hyper::{parse_counts, Bound, Hyper};
 use libmtg::interval::*;
-use libmtg::mtgjson::fetch_set;
-use libmtg::pile::DualPile;
+use libmtg::legality::{validate, Format, LegalityDb};
+use libmtg::mtgjson::{fetch_set, MtgError};
+use libmtg::par;
+use libmtg::pile::{ColoredPile, DualPile, LandPile};
+use libmtg::sideboard::parse_plans;
+use libmtg::sim::{scry_table, simulate};
+use libmtg::stats::deck_stats;
 use libmtg::table::Table;
 use libmtg::table::TableElem::{LStr, RStr, U32 /*, I32, Empty */};
 //use std::path::Path;
@@ -25,7 +33,71 @@ fn rep(c: char, s: usize) -> String {
     repeat(c).take(s).collect()
 }
 
+// The numbers of a `<lands> <deck> ...` command line, with no more lands
+// than cards in the deck.
+fn deck_numbers(args: &[String]) -> Result<Vec<usize>, MtgError> {
+    let n = args
+        .iter()
+        .map(|a| a.parse().map_err(|_| MtgError::General(format!("Expected a number, found '{}'", a))))
+        .collect::<Result<Vec<usize>, MtgError>>()?;
+    if n.len() >= 2 && n[0] > n[1] {
+        return Err(MtgError::General(format!("{} lands in a deck of {} cards", n[0], n[1])));
+    }
+    Ok(n)
+}
+
+// The value of `res`, else the error and `usage` on stderr, and exit.
+fn or_usage<T>(res: Result<T, MtgError>, usage: &str) -> T {
+    res.unwrap_or_else(|e| {
+        eprintln!("Error: {:?}", e);
+        eprintln!("Usage: {}", usage);
+        std::process::exit(2)
+    })
+}
+
 // #[main]
+fn hyper(args: &[String]) -> Result<(), MtgError> {
+    let mut deck = None;
+    let mut draw = None;
+    let mut cats = vec![];
+    let mut conds = vec![];
+
+    for pair in args.chunks(2) {
+        let value = pair.get(1).ok_or_else(|| MtgError::General(format!("Missing value after '{}'", pair[0])))?;
+        let number = || value.parse::<usize>().map_err(|_| MtgError::General(format!("Expected a number, found '{}'", value)));
+        match &*pair[0] {
+            "--deck" => deck = Some(number()?),
+            "--draw" => draw = Some(number()?),
+            "--cat" => cats.extend(parse_counts(value)?),
+            "--at-least" => conds.extend(parse_counts(value)?.into_iter().map(|(c, n)| (c, Bound::AtLeast(n)))),
+            "--at-most" => conds.extend(parse_counts(value)?.into_iter().map(|(c, n)| (c, Bound::AtMost(n)))),
+            "--exactly" => conds.extend(parse_counts(value)?.into_iter().map(|(c, n)| (c, Bound::Exactly(n)))),
+            a => return Err(MtgError::General(format!("Unknown option '{}'", a))),
+        }
+    }
+
+    let deck = deck.ok_or_else(|| MtgError::General("Missing --deck".to_string()))?;
+    let draw = draw.ok_or_else(|| MtgError::General("Missing --draw".to_string()))?;
+    let cats: Vec<(&str, usize)> = cats.iter().map(|(c, n)| (&**c, *n)).collect();
+    let h = Hyper::new(deck, draw, &cats)?;
+
+    println!("");
+    h.table()?.print("Hypergeometric");
+    if !conds.is_empty() {
+        let conds: Vec<(&str, Bound)> = conds.iter().map(|(c, b)| (&**c, *b)).collect();
+        let text: Vec<String> = conds
+            .iter()
+            .map(|&(c, b)| match b {
+                Bound::AtLeast(n) => format!("{} ≥ {}", c, n),
+                Bound::AtMost(n) => format!("{} ≤ {}", c, n),
+                Bound::Exactly(n) => format!("{} = {}", c, n),
+            })
+            .collect();
+        println!("P({}) = {:.2}%", text.join(", "), h.chance(&conds)? * 100.0);
+    }
+    Ok(())
+}
+
 fn main() {
     //use interval::closed;
 
@@ -33,7 +105,58 @@ fn main() {
 
     println!("Starting.");
 
-    let args: Vec<String> = std::env::args().map(|x| x.to_string()).collect();
+    let mut args: Vec<String> = std::env::args().map(|x| x.to_string()).collect();
+
+    // `--classic` anywhere uses the old mulligan rule, e.g. to reproduce
+    // published tables.
+    let mulligan = if args.iter().any(|a| a == "--classic") {
+        Mulligan::Classic
+    } else {
+        Mulligan::London
+    };
+    args.retain(|a| a != "--classic");
+
+    // `--bo1` deals the opening hands as MTG Arena best-of-one does.
+    let opening = if args.iter().any(|a| a == "--bo1") {
+        Opening::Arena(2)
+    } else {
+        Opening::Shuffle
+    };
+    args.retain(|a| a != "--bo1");
+    let rules = Rules::new(mulligan, opening);
+
+    // `--draw` computes these subcommands for the player on the draw; the
+    // others leave it alone, e.g. `hyper` has a `--draw <n>` of its own.
+    let on_the_draw = ["optimal", "explain", "simulate", "scry", "sideboard"];
+    let play = if args.get(1).map_or(false, |a| on_the_draw.contains(&a.as_str()))
+        && args.iter().any(|a| a == "--draw")
+    {
+        args.retain(|a| a != "--draw");
+        PlayDraw::OnTheDraw
+    } else {
+        PlayDraw::OnThePlay
+    };
+
+    // `--threads <n>` computes the tables on at most `n` threads, instead of
+    // one per core.
+    if let Some(i) = args.iter().position(|a| a == "--threads") {
+        match args.get(i + 1).and_then(|n| n.parse().ok()) {
+            Some(n) => par::set_threads(n),
+            None => {
+                eprintln!("Usage: --threads <n>");
+                std::process::exit(2)
+            }
+        }
+        args.drain(i..=i + 1);
+    }
+
+    // `--exact` checks `table` in exact arithmetic.
+    let summary = if args.iter().any(|a| a == "--exact") {
+        summary_c_exact
+    } else {
+        summary_c
+    };
+    args.retain(|a| a != "--exact");
 
     if args.len() == 1 || (args.len() == 2 && (args[1] == "dump" || args[1] == "fetch")) {
         let mut cs = vec![];
@@ -142,7 +265,7 @@ fn main() {
         for a in 0..=17-4 {
             let goal = |hand: DualPile| (hand.a >= 1) || hand.ab >= 1;
             let td = DualPile::new(a, 17 - 4 - a, 4, 0, 23);
-            let rt = dual::turn0(td, 1, goal);
+            let rt = dual::turn0(td, 2, PlayDraw::OnThePlay, goal);
             dp.set(a, 0, LStr(format!("{:?}", td)));
             dp.set(a, 1, RStr(format!("{:6.2}%", rt * 100.0)));
         }
@@ -155,18 +278,111 @@ fn main() {
         };
         if l == 0 {
             for i in closed(16, 18).iter() {
-                summary_c(i, 40);
+                summary(i, 40, rules);
             }
             for i in closed(22, 28).iter() {
-                summary_c(i, 60);
+                summary(i, 60, rules);
             }
         } else if l <= 19 {
-            summary_c(l, 40);
+            summary(l, 40, rules);
         } else {
-            summary_c(l, 60)
+            summary(l, 60, rules)
         }
     } else if args.len() == 2 && args[1] == "frank" {
-        frank_table()
+        frank_table(rules)
+    } else if (args.len() == 5 || args.len() == 7) && args[1] == "optimal" {
+        // mtg optimal <lands> <deck> <cmc> [<colored lands> <colored mana>]
+        // The best mulligan policy for casting a `cmc` spell on turn `cmc`.
+        let n = or_usage(deck_numbers(&args[2..]), "mtg optimal <lands> <deck> <cmc> [<colored lands> <colored mana>]");
+        let (lands, deck, cmc) = (n[0], n[1], n[2].max(1));
+        let (colored, colored_mana) = if n.len() == 5 { (n[3].min(lands), n[4]) } else { (lands, 0) };
+        let pile = ColoredPile::new(colored, lands - colored, deck - lands);
+        let goal = |hand: ColoredPile| hand.colored() >= colored_mana && hand.lands() >= cmc;
+        let best = optimal::solve(&pile, cmc, play, rules, goal);
+        best.keep_table().print("Keep");
+        println!("{}", best.describe());
+        println!("{:.2}% (land window rule: {:.2}%)", best.prob * 100.0,
+                 chance(&pile, cmc, play, rules, &LandWindow, goal) * 100.0);
+    } else if (args.len() == 6 || args.len() == 8) && args[1] == "battlefield" {
+        // mtg battlefield <lands> <deck> <turn> <lands in play> [<colored lands> <colored in play>]
+        let n = or_usage(deck_numbers(&args[2..]),
+                         "mtg battlefield <lands> <deck> <turn> <lands in play> [<colored lands> <colored in play>]");
+        let (lands, deck, turn, in_play) = (n[0], n[1], n[2], n[3]);
+        let (colored, colored_in_play) = if n.len() == 6 { (n[4].min(lands), n[5]) } else { (lands, 0) };
+        let pile = ColoredPile::new(colored, lands - colored, deck - lands);
+        for p in PlayDraw::both() {
+            let res = battlefield::turn0_with(&pile, turn, p, rules, &LandWindow, |b| {
+                b.lands.lands() >= in_play && b.lands.colored() >= colored_in_play
+            });
+            println!("{}: {:.2}%", p.name(), res * 100.0);
+        }
+    } else if (args.len() == 7 || args.len() == 8) && args[1] == "explain" {
+        // mtg explain <lands> <deck> <cmc> <colored lands> <colored mana> [<top>]
+        // The most likely ways to miss casting a `cmc` spell on turn `cmc`.
+        let n = or_usage(deck_numbers(&args[2..]), "mtg explain <lands> <deck> <cmc> <colored lands> <colored mana> [<top>]");
+        let (lands, deck, cmc, colored, colored_mana) = (n[0], n[1], n[2].max(1), n[3].min(n[0]), n[4]);
+        let top = n.get(5).cloned().unwrap_or(10);
+        let pile = ColoredPile::new(colored, lands - colored, deck - lands);
+        let goal = |hand: ColoredPile| hand.colored() >= colored_mana && hand.lands() >= cmc;
+        let ex = explain::explain(&pile, cmc, play, rules, &LandWindow, goal, explain::colored);
+        println!("");
+        ex.table(top).print(&format!("Misses: {:.2}%, {}", ex.prob * 100.0, play.name()));
+        for line in ex.describe(3) {
+            println!("{}", line);
+        }
+    } else if args.len() >= 2 && args[1] == "simulate" {
+        // mtg simulate [--check] <lands> <deck> <cmc> <colored lands> <colored mana> [<games> [<seed>]]
+        let usage = "mtg simulate [--check] <lands> <deck> <cmc> <colored lands> <colored mana> [<games> [<seed>]]";
+        let check = args.iter().any(|a| a == "--check");
+        let rest: Vec<String> = args[2..].iter().filter(|a| *a != "--check").cloned().collect();
+        let n = or_usage(deck_numbers(&rest).and_then(|n| {
+            if n.len() >= 5 && n.len() <= 7 {
+                Ok(n)
+            } else {
+                Err(MtgError::General(format!("Expected 5 to 7 numbers, found {}", n.len())))
+            }
+        }), usage);
+        let (lands, deck, cmc) = (n[0], n[1], n[2]);
+        let (colored, colored_mana) = (n[3].min(lands), n[4]);
+        let games = n.get(5).cloned().unwrap_or(100000);
+        let seed = n.get(6).cloned().unwrap_or(1) as u64;
+        let pile = ColoredPile::new(colored, lands - colored, deck - lands);
+        let goal = |hand: ColoredPile| hand.colored() >= colored_mana && hand.lands() >= cmc;
+        let est = simulate(&pile, cmc, play, rules, &LandWindow, goal, games, seed);
+        println!("{}", est);
+        if check {
+            let exact = chance(&pile, cmc, play, rules, &LandWindow, goal);
+            println!("exact: {:.2}% ({})", exact * 100.0, if est.contains(exact) { "in the interval" } else { "OUTSIDE the interval" });
+        }
+    } else if (args.len() == 6 || args.len() == 7) && args[1] == "scry" {
+        // mtg scry <lands> <deck> <scry lands> <cantrips> [<games>]
+        let n = or_usage(deck_numbers(&args[2..]), "mtg scry <lands> <deck> <scry lands> <cantrips> [<games>]");
+        let (lands, deck, scry_lands, cantrips) = (n[0], n[1], n[2].min(n[0]), n[3].min(n[1] - n[0]));
+        let games = n.get(4).cloned().unwrap_or(100000);
+        println!("");
+        scry_table(lands, deck, scry_lands, cantrips, play, rules, games).print(&format!("Scry, {}", play.name()));
+    } else if args.len() >= 3 && args[1] == "playdraw" {
+        // mtg playdraw <deck>, or mtg playdraw <lands> <deck size> [<colored lands> ...]
+        let numbers: Vec<usize> = args[2..].iter().filter_map(|a| a.parse().ok()).collect();
+        let deck = if numbers.len() == args.len() - 2 && numbers.len() >= 2 {
+            Some((numbers[0], numbers[2..].to_vec(), numbers[1]))
+        } else {
+            libmtg::land::analyze(&*args[2], false)
+                .map(|(l, cs)| (l as usize, cs.iter().map(|&c| c as usize).collect(), 60))
+        };
+        if let Some((lands, colored, size)) = deck {
+            println!("");
+            play_draw(lands, &colored, size, rules).print("Play or draw");
+        }
+    } else if args.len() >= 2 && args[1] == "hyper" {
+        // mtg hyper --deck 60 --draw 10 --cat lands=24 --cat removal=8 [--at-least lands=4,removal=1]
+        //           [--at-most ...] [--exactly ...]
+        if let Err(e) = hyper(&args[2..]) {
+            eprintln!("Error: {:?}", e);
+            eprintln!("Usage: mtg hyper --deck <n> --draw <n> --cat <name>=<count> ... \
+                      [--at-least|--at-most|--exactly <name>=<n>,...]");
+            std::process::exit(2)
+        }
     } else if args.len() == 3 && args[1] == "pow" {
         let a = args[2].parse().unwrap_or(0usize);
         for k in closed(0, 10).iter() {
@@ -287,17 +503,157 @@ fn main() {
             }
         }
         dt.print("Action Dice")
+    } else if args.len() >= 4 && args[1] == "stats" {
+        // mtg stats [--chart] <deck> SET [SET ...]
+        let chart = args[2] == "--chart";
+        let rest = if chart { &args[3..] } else { &args[2..] };
+        if rest.len() < 2 {
+            eprintln!("Usage: mtg stats [--chart] <deck> SET [SET ...]");
+            std::process::exit(2)
+        }
+        let text = match std::fs::read_to_string(&rest[0]) {
+            Ok(d) => d,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(2)
+            }
+        };
+        let cards = rest[1..].iter().flat_map(|s| fetch_set(s)).collect::<Vec<_>>();
+
+        let (deck, diags) = Deck::parse(&text);
+        let (found, more) = libmtg::deck::resolve(&deck.main, &cards);
+        for d in diags.iter().chain(more.iter()) {
+            eprintln!("{}", d.show(&rest[0]));
+        }
+
+        let stats = deck_stats(&found);
+        stats.print();
+        if chart {
+            print!("{}", stats.curve_chart(40));
+        }
+    } else if args.len() == 4 && args[1] == "sideboard" {
+        // mtg sideboard <deck> <plans>
+        let read = |path: &str| match std::fs::read_to_string(path) {
+            Ok(d) => d,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(2)
+            }
+        };
+        let (deck, diags) = Deck::parse(&read(&args[2]));
+        for d in diags.iter() {
+            eprintln!("{}", d.show(&args[2]));
+        }
+        let (plans, diags) = parse_plans(&read(&args[3]));
+        for d in diags.iter() {
+            eprintln!("{}", d.show(&args[3]));
+        }
+
+        let db = libmtg::land::land_db();
+        let (lands, game1) = libmtg::land::find_lands(&deck.main, &db);
+        for d in game1.iter() {
+            eprintln!("{}", d.show(&args[2]));
+        }
+        println!("Game 1");
+        libmtg::land::report(lands);
+
+        for plan in plans.iter() {
+            let (after, diags) = libmtg::sideboard::apply(&deck, plan);
+            for d in diags.iter() {
+                eprintln!("{}", d.show(&args[3]));
+            }
+            if libmtg::diag::has_errors(&diags) {
+                continue;
+            }
+
+            println!("vs {}", plan.matchup);
+            for swap in plan.swaps.iter() {
+                println!("  {}{} {}", if swap.bring_in { '+' } else { '-' }, swap.count, swap.name);
+            }
+            // Names from the deck were reported with game 1, the others
+            // were brought in by the plan.
+            let (lands, diags) = libmtg::land::find_lands(&after.main, &db);
+            for d in diags.iter().filter(|d| !game1.contains(d)) {
+                eprintln!("{}", d.show(&args[3]));
+            }
+            libmtg::land::report(lands);
+            libmtg::sideboard::castability(&deck, &after, &db, play, rules)
+                .print(&format!("vs {}, {}", plan.matchup, play.name()));
+        }
+    } else if args.len() >= 4 && args[1] == "legal" {
+        // mtg legal <format> <deck> [SET ...]
+        let format = match Format::parse(&args[2]) {
+            Some(f) => f,
+            None => {
+                eprintln!("Unknown format '{}'", args[2]);
+                std::process::exit(2)
+            }
+        };
+        let text = match std::fs::read_to_string(&args[3]) {
+            Ok(d) => d,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(2)
+            }
+        };
+        let (deck, diags) = Deck::parse(&text);
+        for d in diags.iter() {
+            eprintln!("{}", d.show(&args[3]));
+        }
+
+        let mut db = LegalityDb::load();
+        for s in &args[4..] {
+            db.add_cards(&fetch_set(s));
+        }
+
+        let violations = validate(&deck, format, &db);
+        for v in violations.iter() {
+            println!("{}", v);
+        }
+        if violations.is_empty() {
+            println!("Legal in {}", format.name());
+        } else {
+            std::process::exit(1)
+        }
+    } else if args.len() == 3 && args[1] == "check" {
+        // Only report problems with the deck list, e.g. for an editor.
+        let deck = match std::fs::read_to_string(&args[2]) {
+            Ok(d) => d,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(2)
+            }
+        };
+        let db = libmtg::land::land_db();
+        let (_, diags) = libmtg::land::parse_lands(&deck, &db);
+        for d in diags.iter() {
+            eprintln!("{}", d.show(&args[2]));
+        }
+        if libmtg::diag::has_errors(&diags) {
+            std::process::exit(1)
+        }
+    } else if args.len() == 3 && args[1] == "--strict" {
+        match libmtg::land::analyze(&*args[2], true) {
+            Some((lands, colored_lands)) => {
+                summary_c(lands as usize, 60, rules);
+                for &clands in &colored_lands {
+                    summary_perc(lands as usize, clands as usize, 60, rules);
+                }
+            }
+            None => std::process::exit(1),
+        }
     } else if args.len() == 2 {
-        let (lands, colored_lands) = libmtg::land::analyze(&*args[1]);
-        summary_c(lands as usize, 60);
-        for &clands in &colored_lands {
-            summary_perc(lands as usize, clands as usize, 60);
+        if let Some((lands, colored_lands)) = libmtg::land::analyze(&*args[1], false) {
+            summary_c(lands as usize, 60, rules);
+            for &clands in &colored_lands {
+                summary_perc(lands as usize, clands as usize, 60, rules);
+            }
         }
     } else if false {
         let l = 26;
         let d = 60;
         for u in closed(0, 4).iter() {
-            libmtg::logic::summary(l, d, u)
+            libmtg::logic::summary(l, d, u, rules)
         }
     }
 }
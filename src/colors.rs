@@ -1,6 +1,8 @@
 use crate::mana::Mana;
+use mtg_derive::PileKeys;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, RustcDecodable)]
+#[derive(PileKeys)]
 pub enum Color { W, U, B, R, G, C }
 
 impl Color {
@@ -28,7 +30,7 @@ impl Color {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, PileKeys)]
 pub enum Allied {
     WU, UB, BR, RG, GW
 }
@@ -45,7 +47,7 @@ impl Allied {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, PileKeys)]
 pub enum Enemy {
     WB, UR, BG, RW, GU
 }
@@ -62,7 +64,7 @@ impl Enemy {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, PileKeys)]
 pub enum Dual {
     A(Allied),
     E(Enemy)
@@ -77,7 +79,7 @@ impl Dual {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, PileKeys)]
 pub enum Shard {
     WUR, UBG, BRW, RGU, GWB
 }
@@ -94,7 +96,7 @@ impl Shard {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, PileKeys)]
 pub enum Wedge {
     WBR, URG, BGW, RWU, GUB
 }
@@ -0,0 +1,187 @@
+use crate::diag::Diagnostic;
+use crate::names::{self, Named};
+
+//
+// Deck lists.
+//
+// A deck list has one `<count> <card name>` line per card, where the count
+// may also be written `4x`. The main deck comes first; a `Sideboard` line
+// (or an `SB:` prefix on a single line) moves cards to the sideboard, and a
+// `Commander` line to the command zone. Empty lines and comments (`//` or
+// `#`) are skipped.
+//
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub count: u32,
+    pub name: String,
+    pub line: usize,
+    pub column: usize, // where the name starts
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Deck {
+    pub main: Vec<Entry>,
+    pub sideboard: Vec<Entry>,
+    pub commander: Vec<Entry>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Section {
+    Main,
+    Sideboard,
+    Commander,
+}
+
+fn header(line: &str) -> Option<Section> {
+    let l = line.trim_end_matches(':').trim().to_lowercase();
+    match &*l {
+        "deck" | "main" | "maindeck" | "main deck" => Some(Section::Main),
+        "sideboard" | "side" | "companion" => Some(Section::Sideboard),
+        "commander" | "commanders" => Some(Section::Commander),
+        _ => None,
+    }
+}
+
+//
+// Parse one line. Returns `Ok(None)` for empty lines and comments.
+//
+pub fn parse_line(line_no: usize, raw: &str) -> Result<Option<Entry>, Diagnostic> {
+    let line = raw.trim();
+
+    if line.len() == 0 || line.starts_with("//") || line.starts_with('#') {
+        return Ok(None);
+    }
+
+    // Columns are 1-based, counted in characters of the raw line.
+    let col0 = 1 + raw.chars().take_while(|c| c.is_whitespace()).count();
+
+    let caps: Vec<&str> = line.splitn(2, ' ').collect();
+
+    if caps.len() != 2 {
+        return Err(Diagnostic::error(line_no, col0,
+            format!("Expected '<count> <card name>', found '{}'", line)));
+    }
+
+    let col1 = col0 + caps[0].chars().count() + 1 +
+        caps[1].chars().take_while(|c| c.is_whitespace()).count();
+
+    let l0 = caps[0].len();
+    let n = if l0 > 1 && caps[0].chars().last().unwrap() == 'x' {
+        caps[0][0..l0 - 1].parse::<u32>()
+    } else {
+        caps[0].parse::<u32>()
+    };
+
+    match n {
+        Ok(n) => Ok(Some(Entry { count: n, name: caps[1].trim().to_string(), line: line_no, column: col1 })),
+        Err(_) => Err(Diagnostic::error(line_no, col0, format!("Could not parse count '{}'", caps[0]))),
+    }
+}
+
+impl Deck {
+    pub fn parse(text: &str) -> (Deck, Vec<Diagnostic>) {
+        let mut deck = Deck { main: vec![], sideboard: vec![], commander: vec![] };
+        let mut diags = vec![];
+        let mut section = Section::Main;
+
+        for (line_no, raw) in text.split('\n').enumerate() {
+            let line_no = line_no + 1;
+
+            if let Some(s) = header(raw) {
+                section = s;
+                continue;
+            }
+
+            // `SB: 2 Duress` puts a single line in the sideboard.
+            let trimmed = raw.trim_start();
+            let (sec, raw, shift) = if trimmed.starts_with("SB:") {
+                let skip = raw.len() - trimmed.len() + 3;
+                (Section::Sideboard, &raw[skip..], raw[..skip].chars().count())
+            } else {
+                (section, raw, 0)
+            };
+
+            match parse_line(line_no, raw) {
+                Ok(Some(mut e)) => {
+                    e.column += shift;
+                    match sec {
+                        Section::Main => deck.main.push(e),
+                        Section::Sideboard => deck.sideboard.push(e),
+                        Section::Commander => deck.commander.push(e),
+                    }
+                }
+                Ok(None) => (),
+                Err(mut d) => {
+                    d.column += shift;
+                    diags.push(d)
+                }
+            }
+        }
+
+        (deck, diags)
+    }
+
+    pub fn main_count(&self) -> u32 {
+        self.main.iter().map(|e| e.count).sum()
+    }
+
+    pub fn sideboard_count(&self) -> u32 {
+        self.sideboard.iter().map(|e| e.count).sum()
+    }
+
+    pub fn commander_count(&self) -> u32 {
+        self.commander.iter().map(|e| e.count).sum()
+    }
+
+    /// The number of copies of `name` in the main deck, sideboard and
+    /// command zone together, comparing folded names.
+    pub fn copies(&self, name: &str) -> u32 {
+        let name = names::fold(name);
+        self.main.iter().chain(self.sideboard.iter()).chain(self.commander.iter())
+            .filter(|e| names::fold(&e.name) == name)
+            .map(|e| e.count)
+            .sum()
+    }
+
+    /// The distinct card names in the deck, main deck first.
+    pub fn card_names(&self) -> Vec<String> {
+        let mut res: Vec<String> = vec![];
+        for e in self.main.iter().chain(self.sideboard.iter()).chain(self.commander.iter()) {
+            if !res.iter().any(|n| names::fold(n) == names::fold(&e.name)) {
+                res.push(e.name.clone())
+            }
+        }
+        res
+    }
+}
+
+//
+// Look up each of `entries` in `db`. Names that don't resolve are reported as
+// errors; names that only resolve after folding are reported as warnings,
+// with the canonical name as the fix.
+//
+pub fn resolve<'a, T: Named>(entries: &[Entry], db: &'a [T]) -> (Vec<(&'a T, u32)>, Vec<Diagnostic>) {
+    let mut diags = vec![];
+
+    let found = entries.iter().filter_map(|e| {
+        match names::resolve(&e.name, db) {
+            Ok(card) => {
+                let ns = card.names();
+                if !ns.contains(&e.name) {
+                    diags.push(Diagnostic::warning(e.line, e.column,
+                        format!("'{}' taken to mean '{}'", e.name, ns[0]))
+                        .with_fix(Some(ns[0].clone())));
+                }
+                Some((card, e.count))
+            }
+            Err(err) => {
+                let fix = if err.ambiguous { None } else { err.suggestions.first().cloned() };
+                diags.push(Diagnostic::error(e.line, e.column, err.to_string()).with_fix(fix));
+                None
+            }
+        }
+    }).collect();
+
+    (found, diags)
+}
@@ -0,0 +1,72 @@
+use std::fmt;
+
+//
+// Diagnostics produced while parsing deck lists.
+//
+// Lines and columns are 1-based and columns are counted in characters, which
+// is what editors expect. A diagnostic prints as
+//
+//     deck.txt:3:3: error: Could not find card named 'Temple of Decet' ...
+//
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Severity::Error => write!(fmt, "error"),
+            Severity::Warning => write!(fmt, "warning"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub line: usize,
+    pub column: usize,
+    pub severity: Severity,
+    pub message: String,
+    /// Replacement text for the offending part of the line, if we have one.
+    pub fix: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn error(line: usize, column: usize, message: String) -> Diagnostic {
+        Diagnostic { line, column, severity: Severity::Error, message, fix: None }
+    }
+
+    pub fn warning(line: usize, column: usize, message: String) -> Diagnostic {
+        Diagnostic { line, column, severity: Severity::Warning, message, fix: None }
+    }
+
+    pub fn with_fix(self, fix: Option<String>) -> Diagnostic {
+        Diagnostic { fix, ..self }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// Format as `file:line:column: severity: message`.
+    pub fn show(&self, file: &str) -> String {
+        format!("{}:{}", file, self)
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}:{}: {}: {}", self.line, self.column, self.severity, self.message)?;
+        if let Some(ref fix) = self.fix {
+            write!(fmt, " [fix: '{}']", fix)?
+        }
+        Ok(())
+    }
+}
+
+pub fn has_errors(diags: &[Diagnostic]) -> bool {
+    diags.iter().any(|d| d.is_error())
+}
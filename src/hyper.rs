@@ -0,0 +1,221 @@
+use crate::mtgjson::MtgError;
+use crate::perm::MultiSubSetIterator;
+use crate::prob;
+use crate::table::Table;
+use crate::table::TableElem::{LStr, RStr, U32};
+
+//
+// The multivariate hypergeometric distribution, over named categories.
+//
+// We draw `draw` cards from a deck of `deck` cards, `count` of which are in
+// each category; the cards in no category are the "other" cards. E.g. the
+// chance of at least 4 lands and a removal spell in the first 10 cards:
+//
+//     let h = Hyper::new(60, 10, &[("lands", 24), ("removal", 8)])?;
+//     h.chance(&[("lands", Bound::AtLeast(4)), ("removal", Bound::AtLeast(1))])?
+//
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Category {
+    pub name: String,
+    pub count: usize,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Bound {
+    Exactly(usize),
+    AtLeast(usize),
+    AtMost(usize),
+}
+
+impl Bound {
+    pub fn holds(self, n: usize) -> bool {
+        match self {
+            Bound::Exactly(k) => n == k,
+            Bound::AtLeast(k) => n >= k,
+            Bound::AtMost(k) => n <= k,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Hyper {
+    pub deck: usize,
+    pub draw: usize,
+    pub categories: Vec<Category>,
+}
+
+impl Hyper {
+    pub fn new(deck: usize, draw: usize, categories: &[(&str, usize)]) -> Result<Hyper, MtgError> {
+        let total: usize = categories.iter().map(|&(_, n)| n).sum();
+        if total > deck {
+            return Err(MtgError::General(format!("The categories have {} cards, the deck only {}", total, deck)));
+        }
+        if draw > deck {
+            return Err(MtgError::General(format!("Can't draw {} cards from {}", draw, deck)));
+        }
+        for (i, &(name, _)) in categories.iter().enumerate() {
+            if categories[..i].iter().any(|&(n, _)| n == name) {
+                return Err(MtgError::General(format!("Category '{}' is given twice", name)));
+            }
+        }
+        let categories = categories.iter().map(|&(name, count)| Category { name: name.to_string(), count }).collect();
+        Ok(Hyper { deck, draw, categories })
+    }
+
+    pub fn others(&self) -> usize {
+        self.deck - self.categories.iter().map(|c| c.count).sum::<usize>()
+    }
+
+    fn index(&self, name: &str) -> Result<usize, MtgError> {
+        self.categories
+            .iter()
+            .position(|c| c.name == name)
+            .ok_or_else(|| MtgError::General(format!("No category '{}'", name)))
+    }
+
+    /// The chance of drawing exactly these many cards of each category, and
+    /// none of the categories not mentioned.
+    pub fn pmf(&self, counts: &[(&str, usize)]) -> Result<f64, MtgError> {
+        let mut drawn = vec![0; self.categories.len()];
+        for &(name, k) in counts {
+            drawn[self.index(name)?] = k;
+        }
+        let cats: usize = drawn.iter().sum();
+        if cats > self.draw || self.draw - cats > self.others() {
+            return Ok(0.0);
+        }
+        let keys = self.categories.len();
+        Ok(prob::hyp(keys + 1, |i| {
+            if i < keys {
+                (self.categories[i].count, drawn[i])
+            } else {
+                (self.others(), self.draw - cats)
+            }
+        }))
+    }
+
+    /// The chance of drawing exactly `k` cards of the category.
+    pub fn exactly(&self, name: &str, k: usize) -> Result<f64, MtgError> {
+        let count = self.categories[self.index(name)?].count as u64;
+        Ok(prob::hyp_pmf(self.deck as u64, count, self.draw as u64, k as u64))
+    }
+
+    /// The chance of drawing at most `k` cards of the category.
+    pub fn at_most(&self, name: &str, k: usize) -> Result<f64, MtgError> {
+        let count = self.categories[self.index(name)?].count as u64;
+        Ok(prob::hyp_cdf(self.deck as u64, count, self.draw as u64, k as u64))
+    }
+
+    /// The chance of drawing at least `k` cards of the category.
+    pub fn at_least(&self, name: &str, k: usize) -> Result<f64, MtgError> {
+        if k == 0 {
+            Ok(1.0)
+        } else {
+            Ok((1.0 - self.at_most(name, k - 1)?).max(0.0))
+        }
+    }
+
+    /// The average number of cards of the category drawn.
+    pub fn expected(&self, name: &str) -> Result<f64, MtgError> {
+        let count = self.categories[self.index(name)?].count;
+        Ok((self.draw * count) as f64 / self.deck as f64)
+    }
+
+    /// The chance of all of `conditions` holding together.
+    pub fn chance(&self, conditions: &[(&str, Bound)]) -> Result<f64, MtgError> {
+        // Only the categories in the conditions matter, the rest count as
+        // other cards.
+        let mut cats: Vec<(usize, Vec<Bound>)> = vec![];
+        for &(name, bound) in conditions {
+            let i = self.index(name)?;
+            match cats.iter_mut().find(|c| c.0 == i) {
+                Some(c) => c.1.push(bound),
+                None => cats.push((i, vec![bound])),
+            }
+        }
+
+        let mut counts: Vec<usize> = cats.iter().map(|&(i, _)| self.categories[i].count).collect();
+        counts.push(self.deck - counts.iter().sum::<usize>());
+
+        let p = MultiSubSetIterator::new(&counts, self.draw)
+            .filter(|drawn| cats.iter().zip(drawn.iter()).all(|(c, &n)| c.1.iter().all(|b| b.holds(n))))
+            .map(|drawn| prob::hyp(counts.len(), |i| (counts[i], drawn[i])))
+            .sum::<f64>();
+        Ok(p.min(1.0))
+    }
+
+    /// For each category, the chance of drawing exactly and at least each
+    /// number of its cards.
+    pub fn table(&self) -> Result<Table, MtgError> {
+        let most = self.categories.iter().map(|c| c.count.min(self.draw)).max().unwrap_or(0);
+        let mut t = Table::new(1 + 2 * self.categories.len(), 3 + most + 1);
+
+        t.set(0, 0, LStr(format!("{} cards, draw {}", self.deck, self.draw)));
+        t.set(0, 1, RStr("#".to_string()));
+        t.set(0, 2, RStr("avg".to_string()));
+        for k in 0..=most {
+            t.set(0, 3 + k, U32(k as u32));
+        }
+
+        for (i, c) in self.categories.iter().enumerate() {
+            let (row_eq, row_ge) = (1 + 2 * i, 2 + 2 * i);
+            t.set(row_eq, 0, LStr(format!("{} =", c.name)));
+            t.set(row_ge, 0, LStr(format!("{} ≥", c.name)));
+            t.set(row_eq, 1, U32(c.count as u32));
+            t.set(row_eq, 2, RStr(format!("{:.2}", self.expected(&c.name)?)));
+            for k in 0..=c.count.min(self.draw) {
+                t.set(row_eq, 3 + k, RStr(format!("{:.1}%", self.exactly(&c.name, k)? * 100.0)));
+                t.set(row_ge, 3 + k, RStr(format!("{:.1}%", self.at_least(&c.name, k)? * 100.0)));
+            }
+        }
+
+        Ok(t)
+    }
+}
+
+/// Parse `name=count,name=count`, as in `lands=24` or `lands=4,removal=1`.
+pub fn parse_counts(s: &str) -> Result<Vec<(String, usize)>, MtgError> {
+    s.split(',')
+        .filter(|item| !item.trim().is_empty())
+        .map(|item| {
+            let mut parts = item.splitn(2, '=');
+            let name = parts.next().unwrap_or("").trim();
+            match parts.next().map(|n| n.trim().parse::<usize>()) {
+                Some(Ok(n)) if !name.is_empty() => Ok((name.to_string(), n)),
+                _ => Err(MtgError::General(format!("Expected '<category>=<count>', found '{}'", item))),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lands_and_removal() {
+        let h = Hyper::new(60, 10, &[("lands", 24), ("removal", 8)]).unwrap();
+
+        // The joint chance over all outcomes adds up.
+        let all: f64 = (0..=10)
+            .flat_map(|l| (0..=8).map(move |r| (l, r)))
+            .map(|(l, r)| h.pmf(&[("lands", l), ("removal", r)]).unwrap())
+            .sum();
+        assert!((all - 1.0).abs() < 1e-12);
+
+        // One category alone is the plain hypergeometric.
+        let ge4 = h.chance(&[("lands", Bound::AtLeast(4))]).unwrap();
+        assert!((ge4 - h.at_least("lands", 4).unwrap()).abs() < 1e-12);
+        assert!((h.expected("lands").unwrap() - 4.0).abs() < 1e-12);
+
+        // Both are less likely than either.
+        let both = h.chance(&[("lands", Bound::AtLeast(4)), ("removal", Bound::AtLeast(1))]).unwrap();
+        assert!(both < ge4 && both < h.at_least("removal", 1).unwrap());
+
+        assert!(h.chance(&[("spells", Bound::AtLeast(1))]).is_err());
+        assert!(Hyper::new(60, 7, &[("lands", 40), ("spells", 30)]).is_err());
+        assert_eq!(parse_counts("lands=4, removal=1").unwrap(), vec![("lands".to_string(), 4), ("removal".to_string(), 1)]);
+        assert!(parse_counts("lands").is_err());
+    }
+}
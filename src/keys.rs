@@ -1,159 +1,128 @@
+pub use crate::colors::{AlliedKeys, ColorKeys, DualKeys, EnemyKeys, ShardKeys, WedgeKeys};
+use crate::colors::{Color, Dual, Enemy, Wedge};
+use mtg_derive::PileKeys;
+
+//
+// Typed keys for piles.
+//
+// A `Keys` maps a key type one to one onto `0..size()`, the positions in a
+// `GenPile<K>`, so a pile can be indexed by e.g. `Land::Shock(Dual::A(WU))`
+// instead of by a position we have to keep track of, and all the keys can be
+// enumerated.
+//
+
+pub trait Keys: Copy + PartialEq {
+    type Key: Copy;
+
+    fn size(&self) -> usize;
+    fn to_uint(&self, k: Self::Key) -> usize;
+    fn from_uint(&self, n: usize) -> Self::Key;
+
+    /// Whether the cards with the key at `n` are lands.
+    fn is_land(&self, _n: usize) -> bool {
+        false
+    }
 
-struct ColorKeys;
+    /// The colors the lands with the key at `n` tap for, as a `pile::mask`,
+    /// or 0 if we don't know.
+    fn land_colors(&self, _n: usize) -> u8 {
+        0
+    }
 
-impl Keys<Color> for ColorKeys {
-    fn size(&self) -> uint { 5 }
-    fn to_uint(&self, c:Color) -> uint { c as uint }
-    fn from_uint(&self, n:uint) -> Color { 
-        match n {
-            0 => W, 1 => U, 2 => B, 3 => R, 4 => G, 
-            _ => fail!("out of range")
-        }
+    /// Whether the lands with the key at `n` enter tapped.
+    fn enters_tapped(&self, _n: usize) -> bool {
+        false
     }
-}
 
-struct AlliedKeys;
-
-impl Keys<Allied> for AlliedKeys {
-    fn size(&self) -> uint { 5 }
-    fn to_uint(&self, c:Allied) -> uint { c as uint }
-    fn from_uint(&self, n:uint) -> Allied { 
-        match n {
-            0 => Some(WU), 1 => Some(UB), 2 => Some(BR), 3 => Some(RG), 4 => Some(GW), 
-            _ => None
-        }.unwrap()
-    }    
+    /// All the keys, in order.
+    fn all(&self) -> Vec<Self::Key> {
+        (0..self.size()).map(|n| self.from_uint(n)).collect()
+    }
 }
 
-struct EnemyKeys;
-
-impl Keys<Enemy> for EnemyKeys {
-    fn size(&self) -> uint { 5 }
-    fn to_uint(&self, c:Enemy) -> uint { c as uint }
-    fn from_uint(&self, n:uint) -> Enemy { 
-        match n {
-            0 => Some(WB), 1 => Some(UR), 2 => Some(BG), 3 => Some(RW), 4 => Some(GU), 
-            _ => None
-        }.unwrap()
-    }    
-}
+/// A key enum that maps itself onto `0..SIZE`, as derived by
+/// `#[derive(PileKeys)]`, which also makes the `Keys` for it, e.g.
+/// `DualKeys` for `Dual`.
+pub trait PileKey: Copy {
+    const SIZE: usize;
 
-struct DualKeys;
+    fn to_uint(self) -> usize;
+    fn from_uint(n: usize) -> Self;
 
-impl Keys<Dual> for DualKeys {
-    fn size(&self) -> uint { 10 }
-    fn to_uint(&self, c:Dual) -> uint { 
-        match c { A(a) => AlliedKeys.to_uint(a), 
-                  E(e) => AlliedKeys.size() + EnemyKeys.to_uint(e)
-        }
+    fn is_land(_n: usize) -> bool {
+        false
     }
-    fn from_uint(&self, n:uint) -> Dual { 
-        match n {
-            0 => Some(A(WU)), 1 => Some(A(UB)), 2 => Some(A(BR)), 3 => Some(A(RG)), 4 => Some(A(GW)), 
-            5 => Some(E(WB)), 6 => Some(E(UR)), 7 => Some(E(BG)), 8 => Some(E(RW)), 9 => Some(E(GU)), 
-            _ => None
-        }.unwrap()
-    }    
 }
 
-// WUBRG
-
-struct ShardKeys;
-
-impl Keys<Shard> for ShardKeys {
-    fn size(&self) -> uint { 5 }
-    fn to_uint(&self, c:Shard) -> uint { c as uint }
-    fn from_uint(&self, n:uint) -> Shard { 
-        match n {
-            0 => Some(WUR), 1 => Some(UBG), 2 => Some(BRW), 3 => Some(RGU), 4 => Some(GWB), 
-            _ => None
-        }.unwrap()
-    }    
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PileKeys)]
+pub enum Special {
+    EvolvingWild,
+    Urborg,
+    ManaConfluence,
 }
 
-
-struct WedgeKeys;
-
-impl Keys<Wedge> for WedgeKeys {
-    fn size(&self) -> uint { 5 }
-    fn to_uint(&self, c:Wedge) -> uint { c as uint }
-    fn from_uint(&self, n:uint) -> Wedge { 
-        match n {
-            0 => Some(WBR), 1 => Some(URG), 2 => Some(BGW), 3 => Some(RWU), 4 => Some(GUB), 
-            _ => None
-        }.unwrap()
-    }    
+/// The lands, by cycle.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PileKeys)]
+#[land]
+pub enum Land {
+    Special(Special),
+    Basic(Color),
+    Shock(Dual),
+    Gate(Dual),
+    Scry(Dual),
+    Refu(Dual),
+    Fetch(Dual),
+    Pain(Enemy),
+    Khan(Wedge),
 }
 
-struct LandKeys;
-
-impl Keys<Land> for LandKeys {
-    fn size(&self) -> uint { ColorKeys.size() + 3 * DualKeys.size() }
-
-    fn to_uint(&self, l:Land) -> uint { 
-        match l {
-            Special(EvolvingWild) => 0,
-            Special(Urborg) => 1,
-            Special(ManaConfluence) => 2,
-            Basic(c) => 3 + ColorKeys.to_uint(c),
-            Shock(d) => 3 + ColorKeys.size() + DualKeys.to_uint(d),
-            Gate(d)  => 3 + ColorKeys.size() + DualKeys.size() + DualKeys.to_uint(d),
-            Scry(d)  => 3 + ColorKeys.size() + 2u * DualKeys.size() + DualKeys.to_uint(d),
-            Refu(d)  => 3 + ColorKeys.size() + 3u * DualKeys.size() + DualKeys.to_uint(d),
-            Fetch(d) => 3 + ColorKeys.size() + 4u * DualKeys.size() + DualKeys.to_uint(d),
-            Pain(e)  => 3 + ColorKeys.size() + 5u * DualKeys.size() + EnemyKeys.to_uint(e),
-            Khan(w)  => 3 + ColorKeys.size() + 5u * DualKeys.size() + EnemyKeys.size() + WedgeKeys.to_uint(w),
-        }
-    }
-
-    fn from_uint(&self, d: uint) -> Land {
-        if d == 0 { Special(EvolvingWild) }
-        else if d == 1 { Special(Urborg) }
-        else if d == 2 { Special(ManaConfluence) }
-        else if d < 3 + ColorKeys.size() { 
-            Basic(ColorKeys.from_uint(d - 2))
-        }
-        else if d < (3 + ColorKeys.size()) + DualKeys.size() { 
-            Shock(DualKeys.from_uint(d - (3 + ColorKeys.size())))
-        }
-        else if d < (3 + ColorKeys.size()) + 2 * DualKeys.size() {
-            Gate(DualKeys.from_uint(d - (3 + ColorKeys.size()) - DualKeys.size()))
-        } 
-        else if d < (3 + ColorKeys.size()) + 3 * DualKeys.size() {
-            Scry(DualKeys.from_uint(d - (3 + ColorKeys.size()) - 2 * DualKeys.size()))
-        }
-        else if d < (3 + ColorKeys.size()) + 4 * DualKeys.size() {
-            Refu(DualKeys.from_uint(d - (3 + ColorKeys.size()) - 3 * DualKeys.size()))
-        }
-        else if d < (3 + ColorKeys.size()) + 5 * DualKeys.size() {
-            Fetch(DualKeys.from_uint(d - (3 + ColorKeys.size()) - 4 * DualKeys.size()))
-        }        
-        else {
-            fail!("out of range")
-        }
-    }
+/// A card is a spell or a land.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PileKeys)]
+pub enum Card {
+    S,
+    L(Land),
 }
 
-struct CardKeys;
-
-impl Keys<Card> for CardKeys {
-    fn size(&self) -> uint { 1 + 35 }
-    fn to_uint(&self, c:Card) -> uint {
-        match c {
-            S   => 0u,
-            L(l) => LandKeys.to_uint(l) + 1u,
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colors::Allied::WU;
+    use crate::pile::{GenPile, LandPile, Pile};
+
+    fn one_to_one<K: Keys>(keys: K)
+    where
+        K::Key: PartialEq + std::fmt::Debug,
+    {
+        let all = keys.all();
+        assert_eq!(all.len(), keys.size());
+        for (n, &k) in all.iter().enumerate() {
+            assert_eq!(keys.to_uint(k), n, "{:?}", k);
         }
     }
-    fn from_uint(&self, v:uint) -> Card { 
-        if v < 1u { 
-            S 
-        }
-        else if v < self.size() {
-            L(LandKeys.from_uint(v - 1u))
-        }
-        else {
-            fail!("out of range")
-        }
+
+    #[test]
+    fn keys() {
+        one_to_one(ColorKeys);
+        one_to_one(AlliedKeys);
+        one_to_one(EnemyKeys);
+        one_to_one(DualKeys);
+        one_to_one(ShardKeys);
+        one_to_one(WedgeKeys);
+        one_to_one(LandKeys);
+        one_to_one(CardKeys);
+        one_to_one(SpecialKeys);
+
+        assert_eq!((ColorKeys.size(), DualKeys.size(), LandKeys.size(), CardKeys.size()), (6, 10, 69, 70));
+        assert_eq!(LandKeys.to_uint(Land::Basic(Color::W)), 3);
+        assert_eq!(LandKeys.from_uint(9), Land::Shock(Dual::A(WU)));
+        assert_eq!(LandKeys.from_uint(68), Land::Khan(Wedge::GUB));
+        assert!(!CardKeys.is_land(0) && (1..70).all(|n| CardKeys.is_land(n)));
+        assert!(!DualKeys.is_land(0));
+
+        let deck = GenPile::of(CardKeys, &[(Card::S, 36), (Card::L(Land::Shock(Dual::A(WU))), 4),
+                                           (Card::L(Land::Basic(Color::W)), 10), (Card::L(Land::Basic(Color::U)), 10)]);
+        assert_eq!((deck.total(), deck.lands()), (60, 24));
+        assert_eq!(deck.cards(Card::L(Land::Shock(Dual::A(WU)))), 4);
+        assert_eq!(deck.counts().filter(|&(_, n)| n > 0).count(), 4);
     }
 }
-
@@ -1,6 +1,9 @@
 //use collections::treemap::TreeMap;
 use crate::mana::Mana;
 use crate::colors::Color::{self, U,W,B,R,G,C};
+use crate::names::{self, Named};
+use crate::diag::{self, Diagnostic};
+use crate::deck::{self, Deck, Entry};
 use rustc_serialize::json;
 use std::path::Path;
 
@@ -97,70 +100,98 @@ impl LandCardInfo {
     }
 }
 
+impl Named for LandCardInfo {
+    fn names(&self) -> Vec<String> {
+        vec![self.name.clone(), self.short.clone()]
+    }
+}
+
 //
+// Parse the main deck of a land list (see `deck` for the format). Lines that
+// can't be used are reported as diagnostics rather than dropped silently, so
+// the caller can tell when the analysis is based on fewer lands than listed.
 //
-pub fn parse_lands<'db>(lands: &str, db: &'db Vec<LandCardInfo>) -> Vec<(&'db LandCardInfo, u32)>
+pub fn parse_lands<'db>(lands: &str, db: &'db Vec<LandCardInfo>)
+                        -> (Vec<(&'db LandCardInfo, u32)>, Vec<Diagnostic>)
 {
-    lands.split('\n').filter_map(|line| {
-        let line = line.trim();
-
-        if line.len() == 0 { return None }
-
-        let caps:Vec<&str> = line.splitn(2, ' ').collect();
+    let (deck, mut diags) = Deck::parse(lands);
+    let (ls, more) = deck::resolve(&deck.main, db);
+    diags.extend(more);
+    diags.sort_by_key(|d| (d.line, d.column));
 
-        if caps.len() != 2 {
-            println!("warning: No space character in line: '{}' ({} parts)", line, caps.len());
-            return None
-        }
-
-        let l0 = caps[0].len();
-        let n = if l0 > 1 && caps[0].chars().last().unwrap() == 'x' {
-            caps[0][0..l0-1].parse::<u32>()
-        } else {
-            caps[0].parse::<u32>()
-        };
-
-        let l = db.iter().find(|&nm| nm.name == caps[1] ||
-                                     nm.short == caps[1]);
+    (ls, diags)
+}
 
-        match (n, l) {
-            (Ok(n), Some(l)) => Some((l, n)),
-            (Err(_), Some(l)) => {
-                println!("Could not parse count on '{}': {}", l.name, caps[0]);
-                None
-            },
-            (Ok(_), None) => {
-                println!("Could not find card named '{}' in database", caps[1]);
-                None
-            },
-            _ => {
-                println!("Invalid line: {} {}", caps[0], caps[1]);
-                None
-            }
-        }
-    }).collect()
+pub fn land_db() -> Vec<LandCardInfo>
+{
+    let text = include_str!("lands.json");
+    json::decode(text).unwrap()
 }
 
-pub fn analyze(deck: &str) -> (u32, Vec<u32>)
+// Analyze the lands in the `deck` file. Parse problems are printed to stderr;
+// with `strict` set, any error stops the analysis and `None` is returned.
+pub fn analyze(deck: &str, strict: bool) -> Option<(u32, Vec<u32>)>
 {
     use std::fs::File;
     use std::io::Read;
 
-    let text = include_str!("lands.json");
-    let db:Vec<LandCardInfo> = json::decode(text).unwrap();
+    let db = land_db();
+    let file_name = deck;
 
     let mut file = match File::open(&Path::new(deck)) {
-        Ok(f) => f, Err(e) => { println!("Error: {}", e); return (0, vec![]); }
+        Ok(f) => f, Err(e) => { eprintln!("Error: {}", e); return None; }
     };
     let mut deck = String::new();
     match file.read_to_string(&mut deck) {
        Ok(_) => (),
-       Err(e) => { println!("Error: {}", e); return (0, vec![]) }
+       Err(e) => { eprintln!("Error: {}", e); return None }
     };
 
     //println!("=========================\n{}================", deck);
 
-    let mut ls : Vec<(&LandCardInfo, u32)> = parse_lands(&*deck, &db);
+    let (ls, diags) = parse_lands(&*deck, &db);
+
+    for d in diags.iter() {
+        eprintln!("{}", d.show(file_name));
+    }
+    if strict && diag::has_errors(&diags) {
+        return None;
+    }
+
+    Some(report(ls))
+}
+
+// The lands among `entries`; anything that isn't in the land database is
+// taken to be a spell. So is a name that could be several lands, but that
+// is reported as a warning.
+pub fn find_lands<'db>(entries: &[Entry], db: &'db Vec<LandCardInfo>)
+                       -> (Vec<(&'db LandCardInfo, u32)>, Vec<Diagnostic>)
+{
+    let mut diags = vec![];
+    let ls = entries.iter()
+        .filter_map(|e| match names::resolve(&e.name, db) {
+            Ok(l) => Some((l, e.count)),
+            Err(err) => {
+                if err.ambiguous {
+                    diags.push(Diagnostic::warning(e.line, e.column, format!("{}, counted as a spell", err)))
+                }
+                None
+            }
+        })
+        .collect();
+    (ls, diags)
+}
+
+// The number of lands and the mana sources they provide.
+pub fn sources(ls: &Vec<(&LandCardInfo, u32)>) -> (u32, Mana)
+{
+    ls.iter().fold((0u32, Mana::zero()), |(l, m), &(c, n)| { (l + n, m + c.source(ls) * n) })
+}
+
+// Print the land tables, and return the number of lands and the number of
+// sources of each color the lands provide.
+pub fn report(mut ls: Vec<(&LandCardInfo, u32)>) -> (u32, Vec<u32>)
+{
     ls.sort_by(|&a, &b| a.0.landtype.cmp(&b.0.landtype));
 
     {
@@ -183,8 +214,7 @@ pub fn analyze(deck: &str) -> (u32, Vec<u32>)
         table.print("Deck");
     }
 
-    let lds = ls.iter()
-        .fold((0u32, Mana::zero()), |(l, m), &(c, n)| { (l + n, m + c.source(&ls) * n) });
+    let lds = sources(&ls);
     let unt = ls.iter().filter(|&&(c, _)| c.untapped())
         .fold((0u32, Mana::zero()), |(l, m), &(c, n)| { (l + n, m + c.source(&ls) * n) });
     let tap = ls.iter().filter(|&&(c, _)| !c.untapped())
@@ -224,3 +254,23 @@ pub fn analyze(deck: &str) -> (u32, Vec<u32>)
 
     return (lds.0, colors);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diag::Severity;
+
+    #[test]
+    fn diagnostics() {
+        let db = land_db();
+        let text = "4 Island\n  2 Undergound Sea\n4x swamp\nSwamp\n";
+        let (ls, diags) = parse_lands(text, &db);
+
+        assert_eq!(ls.iter().map(|&(l, n)| (&*l.name, n)).collect::<Vec<_>>(), vec![("Island", 4), ("Swamp", 4)]);
+        let at: Vec<(usize, usize, Severity)> = diags.iter().map(|d| (d.line, d.column, d.severity)).collect();
+        assert_eq!(at, vec![(2, 5, Severity::Error), (3, 4, Severity::Warning), (4, 1, Severity::Error)]);
+        assert_eq!(diags[0].fix, Some("Underground Sea".to_string()));
+        assert!(diag::has_errors(&diags));
+        assert!(!diag::has_errors(&diags[1..2]));
+    }
+}
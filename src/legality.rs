@@ -0,0 +1,289 @@
+use crate::deck::Deck;
+use crate::mtgjson::{Card, MtgError};
+use crate::names;
+use rustc_serialize::json;
+use std::fmt;
+
+//
+// Format legality.
+//
+// A constructed deck has at least 60 cards in the main deck, at most 15 in
+// the sideboard and at most 4 copies of any card other than basic lands,
+// counting main deck and sideboard together. Commander decks are exactly 100
+// cards counting the commander, one copy of each, but for the cards with a
+// limit of their own (Seven Dwarves, any number of Relentless Rats). On top
+// of that each format has a banned list (and possibly a restricted list, one
+// copy allowed).
+//
+// The lists come from `legality.json`, or a local file in the same format,
+// and can be extended with the legalities MTGJSON reports for each card.
+//
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    Standard,
+    Modern,
+    Legacy,
+    Pauper,
+    Commander,
+}
+
+impl Format {
+    pub fn all() -> Vec<Format> {
+        vec![Format::Standard, Format::Modern, Format::Legacy, Format::Pauper, Format::Commander]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Standard => "Standard",
+            Format::Modern => "Modern",
+            Format::Legacy => "Legacy",
+            Format::Pauper => "Pauper",
+            Format::Commander => "Commander",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Format> {
+        Format::all().into_iter().find(|f| f.name().eq_ignore_ascii_case(s))
+    }
+
+    fn singleton(self) -> bool {
+        self == Format::Commander
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Status {
+    Legal,
+    Banned,
+    Restricted,
+    NotLegal,
+}
+
+impl Status {
+    // As spelled by MTGJSON.
+    fn parse(s: &str) -> Option<Status> {
+        match &*s.to_lowercase() {
+            "legal" => Some(Status::Legal),
+            "banned" => Some(Status::Banned),
+            "restricted" => Some(Status::Restricted),
+            "not legal" => Some(Status::NotLegal),
+            _ => None,
+        }
+    }
+}
+
+pub struct LegalityDb {
+    basics: Vec<String>,
+    limits: Vec<(String, u32)>,           // 0 means any number
+    lists: Vec<(Format, String, Status)>, // folded card names
+}
+
+impl LegalityDb {
+    /// The lists shipped with the program.
+    pub fn load() -> LegalityDb {
+        LegalityDb::parse(include_str!("legality.json")).unwrap()
+    }
+
+    /// Read the lists from a local file in the same format as `legality.json`.
+    pub fn read(path: &str) -> Result<LegalityDb, MtgError> {
+        let text = std::fs::read_to_string(path)?;
+        LegalityDb::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<LegalityDb, MtgError> {
+        fn strings(j: Option<&json::Json>) -> Vec<String> {
+            match j.and_then(|j| j.as_array()) {
+                Some(a) => a.iter().filter_map(|s| s.as_string()).map(|s| s.to_string()).collect(),
+                None => vec![],
+            }
+        }
+
+        let doc = json::Json::from_str(text)?;
+
+        let limits = match doc.find("limits").and_then(|l| l.as_object()) {
+            Some(o) => o.iter()
+                .map(|(n, l)| (names::fold(n), l.as_u64().unwrap_or(0) as u32))
+                .collect(),
+            None => vec![],
+        };
+
+        let mut lists = vec![];
+        for format in Format::all() {
+            let f = doc.find_path(&["formats", format.name()]);
+            for n in strings(f.and_then(|f| f.find("banned"))) {
+                lists.push((format, names::fold(&n), Status::Banned))
+            }
+            for n in strings(f.and_then(|f| f.find("restricted"))) {
+                lists.push((format, names::fold(&n), Status::Restricted))
+            }
+        }
+
+        Ok(LegalityDb {
+            basics: strings(doc.find("basics")).iter().map(|n| names::fold(n)).collect(),
+            limits,
+            lists,
+        })
+    }
+
+    /// Add what MTGJSON says about `cards`. These take precedence over the
+    /// lists we already have.
+    pub fn add_cards(&mut self, cards: &[Card]) {
+        for card in cards {
+            let name = names::fold(&card.card_name);
+            for &(ref f, ref l) in card.legalities.iter() {
+                if let (Some(format), Some(status)) = (Format::parse(f), Status::parse(l)) {
+                    self.lists.retain(|&(lf, ref ln, _)| !(lf == format && *ln == name));
+                    if status != Status::Legal {
+                        self.lists.push((format, name.clone(), status))
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn status(&self, format: Format, name: &str) -> Status {
+        let name = names::fold(name);
+        self.lists.iter()
+            .find(|&&(f, ref n, _)| f == format && *n == name)
+            .map(|&(_, _, s)| s)
+            .unwrap_or(Status::Legal)
+    }
+
+    pub fn is_basic(&self, name: &str) -> bool {
+        let name = names::fold(name);
+        self.basics.iter().any(|b| *b == name)
+    }
+
+    // The most copies of `name` allowed in `format`, `None` if any number is.
+    fn max_copies(&self, format: Format, name: &str) -> Option<u32> {
+        let folded = names::fold(name);
+        if self.is_basic(name) {
+            return None;
+        }
+        match self.limits.iter().find(|&&(ref n, _)| *n == folded) {
+            Some(&(_, 0)) => None,
+            Some(&(_, l)) => Some(l),
+            None => Some(if format.singleton() { 1 } else { 4 }),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Rule {
+    DeckSize,
+    Sideboard,
+    Copies,
+    Banned,
+    Restricted,
+    NotLegal,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Violation {
+    pub card: Option<String>,
+    pub rule: Rule,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}", self.message)
+    }
+}
+
+/// Check `deck` against the rules of `format`, returning every violation.
+pub fn validate(deck: &Deck, format: Format, db: &LegalityDb) -> Vec<Violation> {
+    let mut res = vec![];
+
+    let main = deck.main_count();
+    if format.singleton() {
+        let total = main + deck.commander_count();
+        if total != 100 {
+            res.push(Violation {
+                card: None,
+                rule: Rule::DeckSize,
+                message: format!("{} decks must have exactly 100 cards, this has {}", format.name(), total),
+            })
+        }
+    } else {
+        if main < 60 {
+            res.push(Violation {
+                card: None,
+                rule: Rule::DeckSize,
+                message: format!("{} decks must have at least 60 cards, this has {}", format.name(), main),
+            })
+        }
+        let side = deck.sideboard_count();
+        if side > 15 {
+            res.push(Violation {
+                card: None,
+                rule: Rule::Sideboard,
+                message: format!("Sideboards can have at most 15 cards, this has {}", side),
+            })
+        }
+    }
+
+    for name in deck.card_names() {
+        let copies = deck.copies(&name);
+
+        match db.status(format, &name) {
+            Status::Banned => res.push(Violation {
+                card: Some(name.clone()),
+                rule: Rule::Banned,
+                message: format!("'{}' is banned in {}", name, format.name()),
+            }),
+            Status::NotLegal => res.push(Violation {
+                card: Some(name.clone()),
+                rule: Rule::NotLegal,
+                message: format!("'{}' is not legal in {}", name, format.name()),
+            }),
+            Status::Restricted if copies > 1 => res.push(Violation {
+                card: Some(name.clone()),
+                rule: Rule::Restricted,
+                message: format!("'{}' is restricted in {}: {} copies, at most 1 allowed",
+                                 name, format.name(), copies),
+            }),
+            _ => match db.max_copies(format, &name) {
+                Some(max) if copies > max => res.push(Violation {
+                    card: Some(name.clone()),
+                    rule: Rule::Copies,
+                    message: format!("{} copies of '{}', at most {} allowed in {}",
+                                     copies, name, max, format.name()),
+                }),
+                _ => (),
+            },
+        }
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_modern() {
+        let db = LegalityDb::load();
+        let (deck, _) = Deck::parse("24 Island\n4 Ponder\n5 Opt\n27 Snow-Covered Island\nSideboard\n1 Opt\n");
+        let rules: Vec<Rule> = validate(&deck, Format::Modern, &db).iter().map(|v| v.rule).collect();
+        assert_eq!(rules, vec![Rule::Banned, Rule::Copies]);
+        assert_eq!(deck.copies("opt"), 6);
+    }
+
+    #[test]
+    fn validate_commander() {
+        let db = LegalityDb::load();
+        let list = "Commander\n1 Kenrith, the Returned King\nDeck\n7 Seven Dwarves\n9 Nazgûl\n82 Island\n1 Sol Ring\n";
+        let (deck, _) = Deck::parse(list);
+        assert_eq!((deck.main_count(), deck.commander_count()), (99, 1));
+        assert_eq!(validate(&deck, Format::Commander, &db), vec![]);
+
+        // The commander counts toward the 100 cards, and the card limits
+        // still apply.
+        let (deck, _) = Deck::parse(&list.replace("82 Island", "81 Island\n2 Sol Ring").replace("7 Seven", "8 Seven"));
+        let rules: Vec<Rule> = validate(&deck, Format::Commander, &db).iter().map(|v| v.rule).collect();
+        assert_eq!(rules, vec![Rule::DeckSize, Rule::Copies, Rule::Copies]);
+    }
+}
@@ -9,8 +9,13 @@ extern crate num;
 extern crate unicode_width;
 extern crate unicode_segmentation;
 extern crate url;
+extern crate mtg_derive;
+
+// So that the `PileKeys` derive can name `::libmtg` in this crate too.
+extern crate self as libmtg;
 
 pub mod prob;
+pub mod keys;
 pub mod pile;
 pub mod land;
 pub mod table;
@@ -20,3 +25,14 @@ pub mod mana;
 pub mod colors;
 pub mod mtgjson;
 pub mod logic;
+pub mod names;
+pub mod diag;
+pub mod deck;
+pub mod legality;
+pub mod stats;
+pub mod sideboard;
+pub mod sim;
+pub mod par;
+pub mod hyper;
+pub mod category;
+pub mod solve;
@@ -1,7 +1,13 @@
-use crate::pile::{ColoredPile, DualPile, GenPile, GenPileKeys, LandPile};
+use crate::category::Categories;
+use crate::colors::Color;
+use crate::par;
+use crate::pile::{ColoredPile, DualPile, GenPile, LandPile, Pile};
+use crate::prob::Number;
 use crate::table::Table;
 use crate::table::TableElem::{Empty, LStr, RStr, I32, U32};
+use num::BigRational;
 use std::iter::repeat;
+use std::ops::{Add, Sub};
 use unicode_segmentation::UnicodeSegmentation;
 use unicode_width::UnicodeWidthStr;
 
@@ -13,6 +19,150 @@ use unicode_width::UnicodeWidthStr;
 //  - 5 cards: Mulligan 0 or 5 lands (1, 2, 3 or 4 spells)
 //  - 4 cards: Always kept
 //
+// With the London mulligan (the current rule) we always draw 7 cards, and
+// after the N-th mulligan put N of them on the bottom; the hand is kept when
+// we can bottom cards so that the hand size left is kept by the rule above.
+// With the old rule (Classic, used by the published tables) we draw a fresh
+// hand of 7 - N cards instead.
+//
+// The rule above is only one `MulliganPolicy`, `LandWindow`; any other can be
+// given to the `turn0_with` functions.
+//
+
+/// Whether we start the game, and skip the first draw, or not.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PlayDraw {
+    OnThePlay,
+    OnTheDraw,
+}
+
+impl PlayDraw {
+    /// The number of cards drawn by our `turn`-th turn, not counting the
+    /// opening hand.
+    pub fn draws(self, turn: usize) -> usize {
+        match self {
+            PlayDraw::OnThePlay => turn.saturating_sub(1),
+            PlayDraw::OnTheDraw => turn,
+        }
+    }
+
+    pub fn both() -> Vec<PlayDraw> {
+        vec![PlayDraw::OnThePlay, PlayDraw::OnTheDraw]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PlayDraw::OnThePlay => "on the play",
+            PlayDraw::OnTheDraw => "on the draw",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Mulligan {
+    London,
+    Classic,
+}
+
+/// How the opening hands are dealt.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Opening {
+    /// Straight from the shuffled deck.
+    Shuffle,
+    /// MTG Arena best-of-one: deal this many candidate hands (2 on Arena) and
+    /// keep the one whose land count is closest to the deck's land ratio,
+    /// picking at random between ties. Only the first hand is dealt this way,
+    /// the hands after a mulligan are straight from the shuffled deck.
+    Arena(usize),
+}
+
+impl Opening {
+    // The factor to apply to the chance of a `hand_size` card hand with `l`
+    // lands, for each `l`.
+    fn smoothing<P: LandPile + Pile, N: Number>(self, deck: &P, hand_size: usize) -> Vec<N> {
+        let candidates = match self {
+            Opening::Shuffle => return vec![N::one(); hand_size + 1],
+            Opening::Arena(n) => n,
+        };
+
+        let target = (hand_size * deck.lands()) as f64 / deck.total() as f64;
+        let dist = |l: usize| (l as f64 - target).abs();
+        let q: Vec<N> = (0..=hand_size)
+            .map(|l| {
+                if l <= deck.lands() && hand_size - l <= deck.spells() {
+                    deck.prob_land_as(l, hand_size - l)
+                } else {
+                    N::zero()
+                }
+            })
+            .collect();
+        let sum = |far: &dyn Fn(usize) -> bool| {
+            (0..=hand_size).filter(|&m| far(m)).fold(N::zero(), |s, m| s + q[m].clone())
+        };
+        let pow = |x: &N| (1..candidates).fold(x.clone(), |p, _| p * x.clone());
+
+        (0..=hand_size)
+            .map(|l| {
+                // The best candidate is at the distance of `l` with this
+                // chance, and then has each land count at that distance in
+                // proportion to its chance.
+                let d = dist(l);
+                let at_least = sum(&|m| dist(m) >= d - 1e-9);
+                let further = sum(&|m| dist(m) > d + 1e-9);
+                let here = at_least.clone() - further.clone();
+                if here > N::zero() {
+                    (pow(&at_least) - pow(&further)) / here
+                } else {
+                    N::zero()
+                }
+            })
+            .collect()
+    }
+
+    pub fn name(self) -> String {
+        match self {
+            Opening::Shuffle => "shuffle".to_string(),
+            Opening::Arena(n) => format!("best of {} hands", n),
+        }
+    }
+}
+
+/// The rules for dealing and mulliganing the opening hand.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Rules {
+    pub mulligan: Mulligan,
+    pub opening: Opening,
+}
+
+impl Rules {
+    pub fn new(mulligan: Mulligan, opening: Opening) -> Rules {
+        Rules { mulligan, opening }
+    }
+
+    /// The London mulligan with a shuffled deck.
+    pub fn standard() -> Rules {
+        Rules::new(Mulligan::London, Opening::Shuffle)
+    }
+
+    /// The old mulligan rule with a shuffled deck, as in the published tables.
+    pub fn classic() -> Rules {
+        Rules::new(Mulligan::Classic, Opening::Shuffle)
+    }
+
+    /// MTG Arena best-of-one.
+    pub fn arena() -> Rules {
+        Rules::new(Mulligan::London, Opening::Arena(2))
+    }
+
+    /// How the hand after `mulls` mulligans is dealt.
+    pub fn opening_after(self, mulls: usize) -> Opening {
+        if mulls == 0 { self.opening } else { Opening::Shuffle }
+    }
+}
+
+// We look at hands of 7 down to 4 cards; a hand that isn't kept by then counts
+// as a failure.
+pub(crate) const MULLIGANS: usize = 4;
 
 fn mull_rule(hand_size: usize) -> (usize, usize) {
     match hand_size {
@@ -22,241 +172,1360 @@ fn mull_rule(hand_size: usize) -> (usize, usize) {
         4 => (0, 4),
         _ => panic!("Eh"),
     }
-}
+}
+
+pub trait MulliganPolicy<P> {
+    /// The hand we keep out of `hand` after `mulls` mulligans, i.e. `hand`
+    /// less the cards we put on the bottom, or `None` to mulligan again.
+    ///
+    /// With the London mulligan `hand` has 7 cards and `mulls` of them must
+    /// go to the bottom; with the classic rule it has `7 - mulls` cards and
+    /// nothing goes to the bottom. The exact models may bottom other cards
+    /// than the policy, as many lands and spells, when that does better for
+    /// the goal.
+    fn keep(&self, hand: &P, mulls: usize) -> Option<P>;
+}
+
+impl<P, F> MulliganPolicy<P> for F
+where
+    F: Fn(&P, usize) -> Option<P>,
+{
+    fn keep(&self, hand: &P, mulls: usize) -> Option<P> {
+        self(hand, mulls)
+    }
+}
+
+/// Keep when the land count is in the window of the mulligan rule above.
+/// When bottoming we aim for half of the hand being lands, rounding up, as
+/// far as the rule and the hand allow.
+pub struct LandWindow;
+
+impl<P> MulliganPolicy<P> for LandWindow
+where
+    P: Pile + LandPile + Clone + Sub<Output = P>,
+{
+    fn keep(&self, hand: &P, mulls: usize) -> Option<P> {
+        let hand_size = 7 - mulls;
+        let (lands_min, lands_max) = mull_rule(hand_size);
+
+        let lo = lands_min.max(hand_size.saturating_sub(hand.spells()));
+        let hi = lands_max.min(hand.lands()).min(hand_size);
+        if lo > hi {
+            return None;
+        }
+
+        let lands = ((hand_size + 1) / 2).max(lo).min(hi);
+        let bottom = hand.bottom(hand.lands() - lands, hand.spells() - (hand_size - lands));
+        Some(hand.clone() - bottom)
+    }
+}
+
+// The hand `policy` keeps out of `hand`, and how well it does by `reach`.
+// With the London mulligan the policy tells how many lands and spells go to
+// the bottom, and of those we bottom the cards that leave the best chance of
+// reaching the goal, as a player who knows the goal would: which cards are
+// the least useful depends on the goal, not on the order of the keys of the
+// pile. On a tie we bottom what the policy picked.
+fn keep_best<P, N, R>(policy: &dyn MulliganPolicy<P>, hand: &P, mulls: usize, mut reach: R) -> Option<(P, N)>
+where
+    P: Pile + LandPile + Clone + Sub<Output = P>,
+    N: Number,
+    R: FnMut(&P) -> N,
+{
+    let kept = policy.keep(hand, mulls)?;
+    let bottom = hand.clone() - kept.clone();
+    let mut best = (kept.clone(), reach(&kept));
+    if bottom.total() > 0 {
+        for other in hand.subsets(bottom.total()) {
+            if other.lands() == bottom.lands() && !other.has(&bottom) {
+                let k = hand.clone() - other;
+                let r = reach(&k);
+                if r > best.1 {
+                    best = (k, r)
+                }
+            }
+        }
+    }
+    Some(best)
+}
+
+// The draws of `num_draws` cards from `deck` after `hand` whose cards seen,
+// hand plus draw, `pick` accepts: `visit` gets the cards seen and the chance
+// of the draw, in the order of `deck.subsets`.
+fn draws<P, N>(hand: P, num_draws: usize, deck: &P, pick: &dyn Fn(&P) -> bool, visit: &mut dyn FnMut(P, N))
+where
+    P: Pile + Clone + Add<Output = P>,
+    N: Number,
+{
+    if num_draws > 0 {
+        for draw in deck.subsets(num_draws) {
+            let seen = hand.clone() + draw.clone();
+            if pick(&seen) {
+                visit(seen, deck.prob_draw_as(&draw))
+            }
+        }
+    } else if pick(&hand) {
+        visit(hand, N::one())
+    }
+}
+
+// The chance of reaching `goal` by drawing `num_draws` cards from `deck` after
+// `hand`.
+fn draw<P, G, N>(hand: P, num_draws: usize, deck: &P, goal: &G) -> N
+where
+    P: Pile + Clone + Add<Output = P>,
+    G: Fn(P) -> bool,
+    N: Number,
+{
+    let mut p = N::zero();
+    draws(hand, num_draws, deck, &|seen: &P| goal(seen.clone()), &mut |_, q: N| {
+        p = std::mem::replace(&mut p, N::zero()) + q
+    });
+    p
+}
+
+// For each number of mulligans, the chance of keeping and the chance of
+// keeping and reaching the goal, where `reach` gives the chance of reaching it
+// from a kept hand and the library. With the London mulligan the bottomed
+// cards are never drawn, so the library is the deck less the 7 cards we
+// looked at.
+fn tries<P, N>(deck: &P, rules: Rules, policy: &dyn MulliganPolicy<P>, reach: &dyn Fn(P, &P) -> N) -> Vec<(N, N)>
+where
+    P: Pile + LandPile + Clone + Sub<Output = P>,
+    N: Number,
+{
+    let mut res = vec![(N::zero(), N::zero()); MULLIGANS];
+
+    let mut try_hand = |hand: &P, mulls: usize, smoothing: &[N]| {
+        let rest = deck.clone() - hand.clone();
+        if let Some((_, r)) = keep_best(policy, hand, mulls, |kept: &P| reach(kept.clone(), &rest)) {
+            let p = deck.prob_draw_as::<N>(hand) * smoothing[hand.lands()].clone();
+            let (keep, cast) = res[mulls].clone();
+            res[mulls] = (keep + p.clone(), cast + p * r);
+        }
+    };
+
+    match rules.mulligan {
+        Mulligan::London => {
+            let smoothing: Vec<Vec<N>> = (0..MULLIGANS).map(|mulls| rules.opening_after(mulls).smoothing(deck, 7)).collect();
+            for hand in deck.subsets(7) {
+                for mulls in 0..MULLIGANS {
+                    try_hand(&hand, mulls, &smoothing[mulls])
+                }
+            }
+        }
+        Mulligan::Classic => {
+            for mulls in 0..MULLIGANS {
+                let smoothing = rules.opening_after(mulls).smoothing(deck, 7 - mulls);
+                for hand in deck.subsets(7 - mulls) {
+                    try_hand(&hand, mulls, &smoothing)
+                }
+            }
+        }
+    }
+
+    res
+}
+
+// For each number of mulligans, the hands we keep, each with its chance
+// and the library, as in `tries`.
+fn kept<P>(deck: &P, rules: Rules, policy: &dyn MulliganPolicy<P>, reach: &dyn Fn(P, &P) -> f64) -> Vec<Vec<(f64, P, P)>>
+where
+    P: Pile + LandPile + Clone + Sub<Output = P>,
+{
+    let mut res = vec![vec![]; MULLIGANS];
+
+    let mut try_hand = |hand: &P, mulls: usize, smoothing: &[f64]| {
+        let rest = deck.clone() - hand.clone();
+        if let Some((kept, _)) = keep_best(policy, hand, mulls, |kept: &P| reach(kept.clone(), &rest)) {
+            let p = deck.prob_draw(hand) * smoothing[hand.lands()];
+            res[mulls].push((p, kept, rest))
+        }
+    };
+
+    match rules.mulligan {
+        Mulligan::London => {
+            let smoothing: Vec<Vec<f64>> = (0..MULLIGANS).map(|mulls| rules.opening_after(mulls).smoothing(deck, 7)).collect();
+            for hand in deck.subsets(7) {
+                for mulls in 0..MULLIGANS {
+                    try_hand(&hand, mulls, &smoothing[mulls])
+                }
+            }
+        }
+        Mulligan::Classic => {
+            for mulls in 0..MULLIGANS {
+                let smoothing = rules.opening_after(mulls).smoothing(deck, 7 - mulls);
+                for hand in deck.subsets(7 - mulls) {
+                    try_hand(&hand, mulls, &smoothing)
+                }
+            }
+        }
+    }
+
+    res
+}
+
+fn success<N: Number>(tries: &[(N, N)]) -> N {
+    let mut mull = N::one(); // the chance we mulled before
+    let mut succ = N::zero();
+
+    for (keep, cast) in tries.iter().cloned() {
+        succ = succ + mull.clone() * cast;
+        mull = mull * (N::one() - keep);
+    }
+
+    succ
+}
+
+/// The chance of reaching `goal` by turn `turn` with `policy`, for any kind of
+/// pile.
+pub fn chance<P, G>(
+    deck: &P,
+    turn: usize,
+    play: PlayDraw,
+    rules: Rules,
+    policy: &dyn MulliganPolicy<P>,
+    goal: G,
+) -> f64
+where
+    P: Pile + LandPile + Clone + Add<Output = P> + Sub<Output = P>,
+    G: Fn(P) -> bool,
+{
+    chance_as(deck, turn, play, rules, policy, goal)
+}
+
+/// `chance` computed in `N`, e.g. exactly with `BigRational`.
+pub fn chance_as<P, G, N>(
+    deck: &P,
+    turn: usize,
+    play: PlayDraw,
+    rules: Rules,
+    policy: &dyn MulliganPolicy<P>,
+    goal: G,
+) -> N
+where
+    P: Pile + LandPile + Clone + Add<Output = P> + Sub<Output = P>,
+    G: Fn(P) -> bool,
+    N: Number,
+{
+    success(&tries(deck, rules, policy, &|kept, rest| draw(kept, play.draws(turn), rest, &goal)))
+}
+
+// How the published tables were computed with the classic rule: there the
+// chance of reaching the goal is weighted by the chance of keeping once more.
+fn classic_success<N: Number>(tries: &[(N, N)]) -> N {
+    let mut mull = N::one();
+    let mut succ = N::zero();
+
+    for (keep, cast) in tries.iter().cloned() {
+        succ = succ + mull.clone() * (cast * keep.clone());
+        mull = mull * (N::one() - keep);
+    }
+
+    succ
+}
+
+// The chance the tables show for `deck` with the land window rule: as
+// `chance`, but with the classic rule weighted as in the published tables.
+// Only the functions making the tables use this.
+fn table_chance<P, G, N>(deck: &P, turn: usize, play: PlayDraw, rules: Rules, goal: G) -> N
+where
+    P: Pile + LandPile + Clone + Add<Output = P> + Sub<Output = P>,
+    G: Fn(P) -> bool,
+    N: Number,
+{
+    let tries = tries(deck, rules, &LandWindow, &|kept, rest| draw(kept, play.draws(turn), rest, &goal));
+    match rules.mulligan {
+        Mulligan::London => success(&tries),
+        Mulligan::Classic => classic_success(&tries),
+    }
+}
+
+pub mod single {
+    use super::{LandWindow, MulliganPolicy, PlayDraw, Rules};
+    use crate::pile::{ColoredPile, LandPile};
+    use crate::prob::Number;
+    use crate::solve::{Baseline, Search, Solver};
+
+    pub fn turn0<G>(deck: ColoredPile, turn: usize, play: PlayDraw, goal: G) -> f64
+    where
+        G: Fn(ColoredPile) -> bool,
+    {
+        turn0_with(deck, turn, play, Rules::standard(), &LandWindow, goal)
+    }
+
+    pub fn turn0_with<G>(
+        deck: ColoredPile,
+        turn: usize,
+        play: PlayDraw,
+        rules: Rules,
+        policy: &dyn MulliganPolicy<ColoredPile>,
+        goal: G,
+    ) -> f64
+    where
+        G: Fn(ColoredPile) -> bool,
+    {
+        turn0_as(deck, turn, play, rules, policy, goal)
+    }
+
+    /// `turn0_with` computed in `N`, e.g. exactly with `BigRational`.
+    pub fn turn0_as<G, N>(
+        deck: ColoredPile,
+        turn: usize,
+        play: PlayDraw,
+        rules: Rules,
+        policy: &dyn MulliganPolicy<ColoredPile>,
+        goal: G,
+    ) -> N
+    where
+        G: Fn(ColoredPile) -> bool,
+        N: Number,
+    {
+        super::chance_as(&deck, turn, play, rules, policy, goal)
+    }
+
+    pub fn cards<G>(
+        lands: usize,
+        deck: usize,
+        turn: usize,
+        play: PlayDraw,
+        perc: f64,
+        rules: Rules,
+        goal: G,
+    ) -> i32
+    where
+        G: Fn(ColoredPile) -> bool,
+    {
+        cards_as(lands, deck, turn, play, perc, rules, goal).0
+    }
+
+    /// The smallest number of colored lands for which the chance of reaching
+    /// `goal` is at least `perc` of the chance with all lands colored, or 0
+    /// if there is none. Also returns the ratio of the two chances there.
+    pub fn cards_as<G, N>(
+        lands: usize,
+        deck: usize,
+        turn: usize,
+        play: PlayDraw,
+        perc: N,
+        rules: Rules,
+        goal: G,
+    ) -> (i32, N)
+    where
+        G: Fn(ColoredPile) -> bool,
+        N: Number,
+    {
+        // All lands colored is the baseline.
+        let solver = Solver { threshold: perc, baseline: Baseline::At(lands), search: Search::Scan };
+        let sol = solver.solve(0, lands, |k| {
+            super::table_chance(&ColoredPile::new(k, lands - k, deck - lands), turn, play, rules, |g| goal(g))
+        });
+        match (sol.count, sol.ratio()) {
+            (Some(k), Some(ratio)) => (k as i32, ratio),
+            _ => (0, N::zero()),
+        }
+    }
+
+    // The chance of having `colored_mana` colored sources among `cmc` lands
+    // on turn `cmc`, relative to the chance of having `cmc` lands at all.
+    pub fn castable(
+        lands: usize,
+        colored: usize,
+        deck: usize,
+        cmc: usize,
+        colored_mana: usize,
+        play: PlayDraw,
+        rules: Rules,
+    ) -> f64 {
+        let deck0 = ColoredPile::new(lands, 0, deck - lands);
+        let res0: f64 = super::table_chance(&deck0, cmc, play, rules, |hand: ColoredPile| {
+            hand.colored() >= colored_mana && hand.lands() >= cmc
+        });
+        let deck1 = ColoredPile::new(colored, lands - colored, deck - lands);
+        let res1: f64 = super::table_chance(&deck1, cmc, play, rules, |hand: ColoredPile| {
+            hand.colored() >= colored_mana && hand.lands() >= cmc
+        });
+        res1 / res0
+    }
+
+    pub fn prob_color_screwed(
+        lands: usize,
+        colored: usize,
+        deck: usize,
+        cmc: usize,
+        colored_mana: usize,
+        play: PlayDraw,
+        rules: Rules,
+    ) -> String {
+        format!("{:.1}%", castable(lands, colored, deck, cmc, colored_mana, play, rules) * 100.0)
+    }
+}
+
+// ================================================================================
+
+pub mod dual {
+    use super::{LandWindow, MulliganPolicy, PlayDraw, Rules};
+    use crate::pile::DualPile;
+    use crate::prob::Number;
+    use crate::solve::{Baseline, Search, Solver};
+
+    pub fn turn0<G>(deck: DualPile, turn: usize, play: PlayDraw, goal: G) -> f64
+    where
+        G: Fn(DualPile) -> bool,
+    {
+        turn0_with(deck, turn, play, Rules::standard(), &LandWindow, goal)
+    }
+
+    pub fn turn0_with<G>(
+        deck: DualPile,
+        turn: usize,
+        play: PlayDraw,
+        rules: Rules,
+        policy: &dyn MulliganPolicy<DualPile>,
+        goal: G,
+    ) -> f64
+    where
+        G: Fn(DualPile) -> bool,
+    {
+        turn0_as(deck, turn, play, rules, policy, goal)
+    }
+
+    /// `turn0_with` computed in `N`, e.g. exactly with `BigRational`.
+    pub fn turn0_as<G, N>(
+        deck: DualPile,
+        turn: usize,
+        play: PlayDraw,
+        rules: Rules,
+        policy: &dyn MulliganPolicy<DualPile>,
+        goal: G,
+    ) -> N
+    where
+        G: Fn(DualPile) -> bool,
+        N: Number,
+    {
+        super::chance_as(&deck, turn, play, rules, policy, goal)
+    }
+
+    pub fn cards<G>(
+        lands: usize,
+        deck: usize,
+        uncolored: usize,
+        a_rate: f64,
+        turn: usize,
+        play: PlayDraw,
+        perc: f64,
+        rules: Rules,
+        goal: G,
+    ) -> i32
+    where
+        G: Fn(DualPile) -> bool,
+    {
+        let deck0 = DualPile::new(0, 0, lands, 0, deck - lands);
+        let deck1 = DualPile::new(0, 0, lands - uncolored, uncolored, deck - lands);
+
+        let r0: f64 = super::table_chance(&deck0, turn, play, rules, |g| goal(g));
+        let r1: f64 = super::table_chance(&deck1, turn, play, rules, |g| goal(g));
+
+        if r1 < perc * r0 {
+            return -1;
+        }
+
+        // From no duals up to all colored lands duals, where we know the
+        // threshold is met.
+        let solver = Solver { threshold: perc, baseline: Baseline::Chance(r0), search: Search::Scan };
+        let sol = solver.solve(0, lands - uncolored, |ab| {
+            let mono = lands - ab - uncolored;
+            let a = ((mono as f64) * a_rate + 0.5).round() as usize;
+            let b = mono - a;
+
+            assert!(a + b + ab + uncolored + (deck - lands) == deck);
+
+            let deck0 = DualPile::new(a, b, ab, uncolored, deck - lands);
+            super::table_chance(&deck0, turn, play, rules, |g| goal(g))
+        });
+        sol.count.map_or(-1, |ab| ab as i32)
+    }
+}
+
+// ================================================================================
+
+pub mod multi {
+    use super::{LandWindow, MulliganPolicy, PlayDraw, Rules};
+    use crate::mana::Mana;
+    use crate::pile::ManaPile;
+    use crate::prob::Number;
+
+    pub fn turn0<G>(deck: ManaPile, turn: usize, play: PlayDraw, goal: G) -> f64
+    where
+        G: Fn(ManaPile) -> bool,
+    {
+        turn0_with(deck, turn, play, Rules::standard(), &LandWindow, goal)
+    }
+
+    pub fn turn0_with<G>(
+        deck: ManaPile,
+        turn: usize,
+        play: PlayDraw,
+        rules: Rules,
+        policy: &dyn MulliganPolicy<ManaPile>,
+        goal: G,
+    ) -> f64
+    where
+        G: Fn(ManaPile) -> bool,
+    {
+        turn0_as(deck, turn, play, rules, policy, goal)
+    }
+
+    /// `turn0_with` computed in `N`, e.g. exactly with `BigRational`.
+    pub fn turn0_as<G, N>(
+        deck: ManaPile,
+        turn: usize,
+        play: PlayDraw,
+        rules: Rules,
+        policy: &dyn MulliganPolicy<ManaPile>,
+        goal: G,
+    ) -> N
+    where
+        G: Fn(ManaPile) -> bool,
+        N: Number,
+    {
+        super::chance_as(&deck, turn, play, rules, policy, goal)
+    }
+
+    /// The chance of having the lands to pay for `cost` by turn `turn`.
+    pub fn castable(deck: ManaPile, cost: &Mana, turn: usize, play: PlayDraw, rules: Rules) -> f64 {
+        turn0_with(deck, turn, play, rules, &LandWindow, |hand| hand.can_pay(cost))
+    }
+}
+
+// ================================================================================
+
+mod gen {
+    use super::{LandWindow, Mulligan, MulliganPolicy, PlayDraw, Rules, MULLIGANS};
+    use crate::pile::{GenPile, LandPile, Pile};
+    use crate::prob;
+
+    pub fn turn0<G>(deck: GenPile, turn: usize, play: PlayDraw, goal: G) -> f64
+    where
+        G: Fn(GenPile) -> bool,
+    {
+        turn0_with(deck, turn, play, Rules::standard(), &LandWindow, goal)
+    }
+
+    pub fn turn0_with<G>(
+        deck: GenPile,
+        turn: usize,
+        play: PlayDraw,
+        rules: Rules,
+        policy: &dyn MulliganPolicy<GenPile>,
+        goal: G,
+    ) -> f64
+    where
+        G: Fn(GenPile) -> bool,
+    {
+        Convolution::new(&deck, turn, play, rules).chance(policy, &goal)
+    }
+
+    //
+    // With many kinds of cards, enumerating the hands and, for each hand and
+    // each number of mulligans, the draws after it, is slow. Instead we work
+    // out the chance of each hand and of each draw after it once per deck,
+    // from a table of binomials, and then check as many goals and policies as
+    // we like against them.
+    //
+    // The sums are done in the same order as in `tries`, `draw` and
+    // `prob::hyp`, so the results are the same to the bit.
+    //
+    // The goal is checked once for each pile it is asked about: the piles
+    // drawn from the deck are numbered by their count of each kind of card,
+    // the digits of a number in a mixed base (the deck count plus one for
+    // each kind), so that the number of a hand and a draw is the sum of
+    // their numbers and we can remember the goal in a plain `Vec`. When the
+    // deck has too many piles for that, we just check the goal every time.
+    //
+
+    const MEMO_LIMIT: usize = 1 << 22;
+
+    struct Hand {
+        pile: GenPile,
+        prob: f64,
+        draws: Vec<(GenPile, usize, f64)>,
+    }
+
+    pub struct Convolution {
+        rules: Rules,
+        piles: usize,
+        weights: Option<Vec<usize>>,
+        num_draws: usize,
+        hands: Vec<Vec<Hand>>, // for each number of mulligans with the classic rule, else just one
+        smoothing: Vec<Vec<f64>>, // for each number of mulligans
+    }
+
+    fn number(weights: &Option<Vec<usize>>, pile: &GenPile) -> usize {
+        weights.as_ref().map_or(0, |w| (0..pile.num_keys()).map(|i| pile.get(i) * w[i]).sum())
+    }
+
+    impl Convolution {
+        pub fn new(deck: &GenPile, turn: usize, play: PlayDraw, rules: Rules) -> Convolution {
+            let binom: Vec<Vec<f64>> = (0..=deck.total())
+                .map(|n| (0..=n).map(|k| prob::c(n as u64, k as u64)).collect())
+                .collect();
+            let c = |n: usize, k: usize| if k > n { 0.0 } else { binom[n][k] };
+            let prob_draw = |pile: &GenPile, draw: &GenPile| {
+                if pile.total() as u64 > prob::LOG_SPACE_N {
+                    return pile.prob_draw(draw);
+                }
+                let (n_t, k_t, c_t) = (0..pile.num_keys()).fold((0, 0, 1.0), |(n_t, k_t, c_t), i| {
+                    (n_t + pile.get(i), k_t + draw.get(i), c_t * c(pile.get(i), draw.get(i)))
+                });
+                c_t / c(n_t, k_t)
+            };
+
+            let piles = (0..deck.num_keys()).try_fold(1usize, |n, i| n.checked_mul(deck.get(i) + 1));
+            let (piles, weights) = match piles {
+                Some(n) if n <= MEMO_LIMIT => (n, Some(
+                    (0..deck.num_keys()).scan(1, |w, i| Some(std::mem::replace(w, *w * (deck.get(i) + 1)))).collect(),
+                )),
+                _ => (0, None),
+            };
+            let num_draws = play.draws(turn);
+            let sizes: Vec<usize> = match rules.mulligan {
+                Mulligan::London => vec![7],
+                Mulligan::Classic => (0..MULLIGANS).map(|mulls| 7 - mulls).collect(),
+            };
+
+            let hands = sizes
+                .iter()
+                .map(|&size| {
+                    deck.subsets(size)
+                        .map(|hand| {
+                            let rest = deck.clone() - hand.clone();
+                            let draws = rest.subsets(num_draws).map(|d| {
+                                let p = prob_draw(&rest, &d);
+                                (d.clone(), number(&weights, &d), p)
+                            });
+                            Hand { prob: prob_draw(deck, &hand), pile: hand, draws: draws.collect() }
+                        })
+                        .collect()
+                })
+                .collect();
+            let smoothing = (0..MULLIGANS)
+                .map(|mulls| rules.opening_after(mulls).smoothing(deck, sizes[mulls.min(sizes.len() - 1)]))
+                .collect();
+
+            Convolution { rules, piles, weights, num_draws, hands, smoothing }
+        }
+
+        /// The same as `turn0_with` for the deck, turn and rules this was
+        /// built for.
+        pub fn chance(&self, policy: &dyn MulliganPolicy<GenPile>, goal: &dyn Fn(GenPile) -> bool) -> f64 {
+            let mut res = vec![(0.0, 0.0); MULLIGANS];
+            // For each pile, 0 if we haven't checked the goal yet, else 1 or 2.
+            let mut seen = vec![0u8; self.piles];
+
+            let mut try_hand = |hand: &Hand, mulls: usize, smoothing: &[f64]| {
+                let reach = |kept: &GenPile| {
+                    if self.num_draws == 0 {
+                        return prob::cond(goal(kept.clone()));
+                    }
+                    let k = number(&self.weights, kept);
+                    let mut reached = |draw: &GenPile, d: usize| {
+                        if seen.is_empty() {
+                            return goal(kept.clone() + draw.clone());
+                        }
+                        if seen[k + d] == 0 {
+                            seen[k + d] = 1 + goal(kept.clone() + draw.clone()) as u8;
+                        }
+                        seen[k + d] == 2
+                    };
+                    hand.draws
+                        .iter()
+                        .filter(|&(draw, d, _)| reached(draw, *d))
+                        .map(|&(_, _, p)| p)
+                        .sum()
+                };
+                if let Some((_, reach)) = super::keep_best(policy, &hand.pile, mulls, reach) {
+                    let p = hand.prob * smoothing[hand.pile.lands()];
+                    res[mulls].0 += p;
+                    res[mulls].1 += p * reach;
+                }
+            };
+
+            match self.rules.mulligan {
+                Mulligan::London => {
+                    for hand in self.hands[0].iter() {
+                        for mulls in 0..MULLIGANS {
+                            try_hand(hand, mulls, &self.smoothing[mulls])
+                        }
+                    }
+                }
+                Mulligan::Classic => {
+                    for mulls in 0..MULLIGANS {
+                        for hand in self.hands[mulls].iter() {
+                            try_hand(hand, mulls, &self.smoothing[mulls])
+                        }
+                    }
+                }
+            }
+
+            super::success(&res)
+        }
+    }
+}
+
+// ================================================================================
+
+//
+// The mulligan policy that maximizes the chance of reaching a goal, by
+// backward induction over the number of mulligans: with 4 cards we keep
+// anything, and with more we keep a hand (bottoming the best cards) when that
+// does at least as well as taking one more mulligan.
+//
+pub mod optimal {
+    use super::{Mulligan, MulliganPolicy, PlayDraw, Rules, MULLIGANS};
+    use crate::pile::{LandPile, Pile};
+    use crate::table::Table;
+    use crate::table::TableElem::{LStr, RStr, U32};
+    use std::collections::HashMap;
+    use std::ops::{Add, Sub};
+
+    fn key<P: Pile>(p: &P) -> Vec<usize> {
+        (0..p.num_keys()).map(|k| p.get(k)).collect()
+    }
+
+    pub struct Optimal<P> {
+        pub rules: Rules,
+        /// The chance of reaching the goal with this policy.
+        pub prob: f64,
+        // Per number of mulligans, the hand kept for each hand looked at.
+        decisions: Vec<HashMap<Vec<usize>, Option<P>>>,
+        // Per number of mulligans and lands in the hand looked at, the chance
+        // of the hand and of keeping it.
+        by_lands: Vec<Vec<(f64, f64)>>,
+    }
+
+    pub fn solve<P, G>(deck: &P, turn: usize, play: PlayDraw, rules: Rules, goal: G) -> Optimal<P>
+    where
+        P: Pile + LandPile + Clone + Add<Output = P> + Sub<Output = P>,
+        G: Fn(P) -> bool,
+    {
+        let num_draws = play.draws(turn);
+        let mut decisions = vec![HashMap::new(); MULLIGANS];
+        let mut by_lands = vec![vec![(0.0, 0.0); 8]; MULLIGANS];
+
+        let mut next = 0.0; // the chance of success after one more mulligan
+        for mulls in (0..MULLIGANS).rev() {
+            let hand_size = match rules.mulligan {
+                Mulligan::London => 7,
+                Mulligan::Classic => 7 - mulls,
+            };
+
+            let smoothing: Vec<f64> = rules.opening_after(mulls).smoothing(deck, hand_size);
+            let mut value = 0.0;
+            for hand in deck.subsets(hand_size) {
+                let p = deck.prob_draw(&hand) * smoothing[hand.lands()];
+                let rest = deck.clone() - hand.clone();
+
+                let mut best: Option<(f64, P)> = None;
+                for bottom in hand.subsets(hand_size + mulls - 7) {
+                    let kept = hand.clone() - bottom;
+                    let q = super::draw(kept.clone(), num_draws, &rest, &goal);
+                    if best.as_ref().map_or(true, |b| q > b.0) {
+                        best = Some((q, kept))
+                    }
+                }
+
+                let lands = &mut by_lands[mulls][hand.lands()];
+                lands.0 += p;
+                let decision = match best {
+                    Some((q, kept)) if q >= next => {
+                        lands.1 += p;
+                        value += p * q;
+                        Some(kept)
+                    }
+                    _ => {
+                        value += p * next;
+                        None
+                    }
+                };
+                decisions[mulls].insert(key(&hand), decision);
+            }
+            next = value;
+        }
+
+        Optimal { rules, prob: next, decisions, by_lands }
+    }
+
+    impl<P: Pile + Clone> MulliganPolicy<P> for Optimal<P> {
+        fn keep(&self, hand: &P, mulls: usize) -> Option<P> {
+            self.decisions.get(mulls).and_then(|d| d.get(&key(hand)).cloned()).unwrap_or(None)
+        }
+    }
+
+    fn ranges(lands: &[usize]) -> String {
+        let mut res: Vec<String> = vec![];
+        let mut i = 0;
+        while i < lands.len() {
+            let mut j = i;
+            while j + 1 < lands.len() && lands[j + 1] == lands[j] + 1 {
+                j += 1
+            }
+            res.push(if i == j { lands[i].to_string() } else { format!("{}–{}", lands[i], lands[j]) });
+            i = j + 1;
+        }
+        res.join(", ")
+    }
+
+    impl<P> Optimal<P> {
+        fn hand_label(&self, mulls: usize) -> String {
+            match self.rules.mulligan {
+                Mulligan::London if mulls > 0 => format!("{} (from 7)", 7 - mulls),
+                _ => (7 - mulls).to_string(),
+            }
+        }
+
+        // Land counts of the hands looked at that we always, and only
+        // sometimes, keep.
+        fn kept_lands(&self, mulls: usize) -> (Vec<usize>, Vec<usize>) {
+            let ls = &self.by_lands[mulls];
+            let always = (0..ls.len()).filter(|&l| ls[l].0 > 0.0 && ls[l].1 >= ls[l].0 * (1.0 - 1e-9)).collect();
+            let sometimes = (0..ls.len()).filter(|&l| ls[l].1 > 0.0 && ls[l].1 < ls[l].0 * (1.0 - 1e-9)).collect();
+            (always, sometimes)
+        }
+
+        /// The policy in words, e.g. "keep 7 with 2–5 lands; keep 6 with 2–4 lands".
+        pub fn describe(&self) -> String {
+            (0..MULLIGANS)
+                .map(|mulls| {
+                    let (always, sometimes) = self.kept_lands(mulls);
+                    let hand = self.hand_label(mulls);
+                    match (always.is_empty(), sometimes.is_empty()) {
+                        (true, true) => format!("mulligan {}", hand),
+                        (true, false) => format!("keep {} with some {} land hands", hand, ranges(&sometimes)),
+                        (false, true) => format!("keep {} with {} lands", hand, ranges(&always)),
+                        (false, false) => format!("keep {} with {} lands and some {} land hands",
+                                                  hand, ranges(&always), ranges(&sometimes)),
+                    }
+                })
+                .collect::<Vec<String>>()
+                .join("; ")
+        }
+
+        /// How often we keep, by the number of lands in the hand looked at.
+        pub fn keep_table(&self) -> Table {
+            let mut table = Table::new(1 + MULLIGANS, 9);
+            table.set(0, 0, LStr("Hand/lands".to_string()));
+            for l in 0..8 {
+                table.set(0, 1 + l, U32(l as u32));
+            }
+            for mulls in 0..MULLIGANS {
+                table.set(1 + mulls, 0, LStr(self.hand_label(mulls)));
+                for (l, &(p, kept)) in self.by_lands[mulls].iter().enumerate() {
+                    if p > 0.0 {
+                        let cell = if kept >= p * (1.0 - 1e-9) {
+                            "keep".to_string()
+                        } else if kept == 0.0 {
+                            "mull".to_string()
+                        } else {
+                            format!("{:.0}%", kept / p * 100.0)
+                        };
+                        table.set(1 + mulls, 1 + l, RStr(cell));
+                    }
+                }
+            }
+            table
+        }
+    }
+}
+
+// ================================================================================
+
+//
+// Why a goal is missed: the cards seen by the turn, hand plus draws, of the
+// games that miss it, grouped by a description of the cards, e.g. "2 lands,
+// 0 colored, 5 spells", and ranked by their chance. The chances are those of
+// `chance`, so they add up to one minus it; with the classic rule the
+// published tables weigh the tries differently (see `table_chance`).
+//
+
+pub mod explain {
+    use super::{MulliganPolicy, PlayDraw, Rules, MULLIGANS};
+    use crate::pile::{ColoredPile, DualPile, LandPile, Pile};
+    use crate::table::Table;
+    use crate::table::TableElem::{LStr, RStr};
+    use std::collections::HashMap;
+    use std::ops::{Add, Sub};
+
+    pub struct Explanation {
+        /// The chance of missing the goal.
+        pub prob: f64,
+        /// The ways to miss it, most likely first, with their chances.
+        pub misses: Vec<(String, f64)>,
+    }
+
+    pub fn explain<P, G, D>(
+        deck: &P,
+        turn: usize,
+        play: PlayDraw,
+        rules: Rules,
+        policy: &dyn MulliganPolicy<P>,
+        goal: G,
+        describe: D,
+    ) -> Explanation
+    where
+        P: Pile + LandPile + Clone + Add<Output = P> + Sub<Output = P>,
+        G: Fn(P) -> bool,
+        D: Fn(&P) -> String,
+    {
+        let num_draws = play.draws(turn);
+        let mut by_desc: HashMap<String, f64> = HashMap::new();
+
+        let mut mull = 1.0; // the chance we mulled before
+        let reach = |kept: P, rest: &P| super::draw(kept, num_draws, rest, &goal);
+        for hands in super::kept(deck, rules, policy, &reach) {
+            let mut keep = 0.0;
+            for (p, kept, rest) in hands {
+                keep += p;
+                // The draws that miss the goal.
+                super::draws(kept, num_draws, &rest, &|seen: &P| !goal(seen.clone()), &mut |seen, q: f64| {
+                    *by_desc.entry(describe(&seen)).or_insert(0.0) += mull * p * q
+                });
+            }
+            mull *= 1.0 - keep;
+        }
+        if mull > 0.0 {
+            *by_desc.entry(format!("no hand kept in {} tries", MULLIGANS)).or_insert(0.0) += mull;
+        }
+
+        let mut misses: Vec<(String, f64)> = by_desc.into_iter().filter(|&(_, p)| p > 0.0).collect();
+        misses.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then_with(|| a.0.cmp(&b.0)));
+        Explanation { prob: misses.iter().map(|m| m.1).sum(), misses }
+    }
+
+    /// The cards of a `ColoredPile`, e.g. "2 lands, 0 colored, 5 spells".
+    pub fn colored(p: &ColoredPile) -> String {
+        format!("{} lands, {} colored, {} spells", p.lands(), p.colored(), p.spells())
+    }
+
+    /// The cards of a `DualPile`, e.g. "3 lands (1 A, 0 B, 2 AB, 0 colorless), 4 spells".
+    pub fn dual(p: &DualPile) -> String {
+        format!("{} lands ({} A, {} B, {} AB, {} colorless), {} spells", p.lands(), p.a, p.b, p.ab, p.x, p.s)
+    }
+
+    impl Explanation {
+        /// The share of the misses of the `i`-th way to miss.
+        pub fn share(&self, i: usize) -> f64 {
+            if self.prob > 0.0 { self.misses[i].1 / self.prob } else { 0.0 }
+        }
+
+        /// The `top` most likely ways to miss, e.g. "38% of failures: 2 lands,
+        /// 0 colored, 5 spells".
+        pub fn describe(&self, top: usize) -> Vec<String> {
+            (0..self.misses.len().min(top))
+                .map(|i| format!("{:.0}% of failures: {}", self.share(i) * 100.0, self.misses[i].0))
+                .collect()
+        }
+
+        /// The `top` most likely ways to miss, with their chance and share,
+        /// and a last row for the others.
+        pub fn table(&self, top: usize) -> Table {
+            let shown = self.misses.len().min(top);
+            let others = self.misses.len() - shown;
+            let mut table = Table::new(1 + shown + if others > 0 { 1 } else { 0 }, 3);
+            table.set(0, 0, RStr("chance".to_string()));
+            table.set(0, 1, RStr("share".to_string()));
+            table.set(0, 2, LStr("cards seen".to_string()));
+            for i in 0..shown {
+                table.set(1 + i, 0, RStr(format!("{:.2}%", self.misses[i].1 * 100.0)));
+                table.set(1 + i, 1, RStr(format!("{:.1}%", self.share(i) * 100.0)));
+                table.set(1 + i, 2, LStr(self.misses[i].0.clone()));
+            }
+            if others > 0 {
+                let rest: f64 = self.misses[shown..].iter().map(|m| m.1).sum();
+                table.set(1 + shown, 0, RStr(format!("{:.2}%", rest * 100.0)));
+                table.set(1 + shown, 1, RStr(format!("{:.1}%", rest / self.prob * 100.0)));
+                table.set(1 + shown, 2, LStr(format!("{} others", others)));
+            }
+            table
+        }
+    }
+}
+
+// ================================================================================
+
+//
+// Turn by turn: we draw a card each turn (but the first on the play) and play
+// a land when we have one, so goals see the lands on the battlefield and the
+// cards left in hand rather than all the cards seen.
+//
+// With one card a turn we make a land drop every turn as long as we have seen
+// as many lands as turns, so goals like "`cmc` lands by turn `cmc`" come out
+// the same as with `turn0`. Goals on exact counts, on lands beyond the turn or
+// on what is left in hand need this.
+//
+pub mod battlefield {
+    use super::{LandWindow, MulliganPolicy, PlayDraw, Rules};
+    use crate::pile::{LandPile, Pile};
+    use std::collections::BTreeMap;
+    use std::ops::{Add, Sub};
+
+    /// Where we are at the end of a turn.
+    #[derive(Clone, Debug)]
+    pub struct Board<P> {
+        /// The lands on the battlefield.
+        pub lands: P,
+        /// The cards in hand.
+        pub hand: P,
+    }
+
+    /// What a spell or a land does besides making mana.
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub enum Effect {
+        Draw(usize),
+        /// Look at the top cards and put any of them on the bottom.
+        Scry(usize),
+        /// Look at the top cards and put any of them in the graveyard. For us
+        /// the same as scrying, as we never get to the bottom.
+        Surveil(usize),
+    }
+
+    pub trait LookPolicy<P> {
+        /// Whether to keep `card`, seen with a scry or a surveil, on top.
+        fn keep(&self, board: &Board<P>, card: &P) -> bool;
+    }
+
+    impl<P, F> LookPolicy<P> for F
+    where
+        F: Fn(&Board<P>, &P) -> bool,
+    {
+        fn keep(&self, board: &Board<P>, card: &P) -> bool {
+            self(board, card)
+        }
+    }
+
+    /// Keep lands on top until we have this many lands in play and in hand,
+    /// and after that spells, so we bottom lands when flooded.
+    pub struct LandsUntil(pub usize);
+
+    impl<P: LandPile> LookPolicy<P> for LandsUntil {
+        fn keep(&self, board: &Board<P>, card: &P) -> bool {
+            (card.lands() > 0) == (board.lands.lands() + board.hand.lands() < self.0)
+        }
+    }
+
+    /// A spell that draws or looks at cards, like Opt. We cast it whenever
+    /// it's in hand and we have the mana, after the land drop.
+    pub struct Cantrip<P> {
+        /// A pile of just the card.
+        pub card: P,
+        /// The number of lands it taps.
+        pub cost: usize,
+        /// What it does, in order.
+        pub effects: Vec<Effect>,
+        /// Whether the lands in play have the colors for it.
+        pub colors: Box<dyn Fn(&P) -> bool>,
+    }
 
-mod single {
-    use crate::pile::{ColoredPile, LandPile, Pile};
-    use crate::prob;
+    /// A land that does something when played, like a scry land.
+    pub struct LandEffect<P> {
+        /// A pile of just the card.
+        pub card: P,
+        pub tapped: bool,
+        pub effects: Vec<Effect>,
+    }
 
-    fn draw<G: Fn(ColoredPile) -> bool>(hand: ColoredPile, num_draws: usize, deck: ColoredPile, goal: G) -> f64
-    {
-        if num_draws > 0 {
-            ColoredPile::foreach_possible(num_draws)
-                // Make sure draw was possible and that the initial hand + the potential draw satisfies the `goal`.
-                .filter(|draw| deck.has(draw) && goal(hand + *draw))
-                // Calculate the probability of drawing `draw` given the `deck`.
-                .map(|draw| deck.prob_draw(&draw))
-                .sum()
-        } else {
-            prob::cond(goal(hand))
-        }
+    /// The cards that do something, and how we scry and surveil.
+    pub struct Effects<P> {
+        pub cantrips: Vec<Cantrip<P>>,
+        pub lands: Vec<LandEffect<P>>,
+        pub look: Box<dyn LookPolicy<P>>,
     }
 
-    fn intern<G: Fn(ColoredPile) -> bool>(hand_size: usize, deck: ColoredPile, num_draws: usize, goal: G) -> (f64, f64)
-    {
-        let (lands_min, lands_max) = super::mull_rule(hand_size);
+    fn key<P: Pile>(ps: &[&P]) -> Vec<usize> {
+        ps.iter().flat_map(|p| (0..p.num_keys()).map(move |k| p.get(k))).collect()
+    }
 
-        // Probability of keeping
-        let keep: f64 = (lands_min..=lands_max)
-            .map(|lands| deck.prob_land(lands, hand_size - lands))
-            .sum();
+    //
+    // With effects, a state also has the cards we know are on top of the
+    // library, in order, which are no longer in `library`, and how much mana
+    // we have left this turn. Cards we scry to the bottom or surveil away are
+    // gone for good.
+    //
 
-        // Probability of casting (where we auto-fail if we don't have the lands)
-        let cast: f64 = ColoredPile::foreach_possible(hand_size)
-            .filter(|hand| hand.lands() >= lands_min && hand.lands() <= lands_max)
-            .filter(|hand| deck.has(hand))
-            .map(|hand| (deck.prob_draw(&hand) * draw(hand, num_draws, deck - hand, |g| goal(g))))
-            .sum();
+    #[derive(Clone)]
+    struct State<P> {
+        p: f64,
+        library: P,
+        top: Vec<P>,
+        board: Board<P>,
+        mana: usize,
+        dropped: bool,
+    }
 
-        // So cast * keep = chance of reaching goals, *given* no mulligan.
-        (keep, cast)
+    // Merge the states that are the same, adding up their chances.
+    fn merge<P: Pile>(states: Vec<State<P>>) -> Vec<State<P>> {
+        let mut merged: BTreeMap<Vec<usize>, State<P>> = BTreeMap::new();
+        for s in states {
+            let mut k = key(&[&s.library, &s.board.hand, &s.board.lands]);
+            k.extend(s.top.iter().flat_map(|c| key(&[c])));
+            k.extend(&[s.top.len(), s.mana, s.dropped as usize]);
+            match merged.get_mut(&k) {
+                Some(e) => e.p += s.p,
+                None => {
+                    merged.insert(k, s);
+                }
+            }
+        }
+        merged.into_iter().map(|(_, s)| s).collect()
     }
 
-    pub fn turn0<G>(deck: ColoredPile, num_draws: usize, goal: G) -> f64
+    // Take the top card off the library: the one we know, if any, else each
+    // card the library can have there.
+    fn take<P>(s: State<P>) -> Vec<(State<P>, Option<P>)>
     where
-        G: Fn(ColoredPile) -> bool,
+        P: Pile + LandPile + Clone + Add<Output = P> + Sub<Output = P>,
     {
-        let mut mull = 1.0; // the chance we mulled before
-        let mut succ = 0.0;
-
-        for hand_size in (4..=7).rev() {
-            let (keep, cast) = intern(hand_size, deck, num_draws, |g| goal(g));
-            succ += mull * (cast * keep);
-            mull *= 1.0 - keep;
+        let mut s = s;
+        if !s.top.is_empty() {
+            let card = s.top.remove(0);
+            return vec![(s, Some(card))];
         }
-
-        succ
+        if s.library.total() == 0 {
+            return vec![(s, None)];
+        }
+        s.library
+            .subsets(1)
+            .map(|card| {
+                let mut next = s.clone();
+                next.p = s.p * s.library.prob_draw(&card);
+                next.library = s.library.clone() - card.clone();
+                (next, Some(card))
+            })
+            .collect()
     }
 
-    pub fn cards<G>(lands: usize, deck: usize, num_draws: usize, perc: f64, goal: G) -> i32
+    fn look<P>(s: State<P>, n: usize, policy: &dyn LookPolicy<P>) -> Vec<State<P>>
     where
-        G: Fn(ColoredPile) -> bool,
+        P: Pile + LandPile + Clone + Add<Output = P> + Sub<Output = P>,
     {
-        let deck1 = ColoredPile::new(lands, 0, deck - lands);
-        let r1 = turn0(deck1, num_draws, |g| goal(g));
-
-        for k in 0..=lands {
-            let deck0 = ColoredPile::new(k, lands - k, deck - lands);
-            let r0 = turn0(deck0, num_draws, |g| goal(g));
-            if r0 >= perc * r1 {
-                return k as i32;
-            }
+        let mut seen = vec![(s, vec![])];
+        for _ in 0..n {
+            seen = seen
+                .into_iter()
+                .flat_map(|(s, cards): (State<P>, Vec<P>)| {
+                    take(s).into_iter().map(move |(s, card)| {
+                        let mut cards = cards.clone();
+                        cards.extend(card);
+                        (s, cards)
+                    })
+                })
+                .collect();
         }
-        return 0;
+        seen.into_iter()
+            .map(|(mut s, cards)| {
+                let mut top: Vec<P> = cards.into_iter().filter(|card| policy.keep(&s.board, card)).collect();
+                top.append(&mut s.top);
+                s.top = top;
+                s
+            })
+            .collect()
     }
 
-    pub fn prob_color_screwed(
-        lands: usize,
-        colored: usize,
-        deck: usize,
-        cmc: usize,
-        colored_mana: usize,
-    ) -> String {
-        let deck0 = ColoredPile::new(lands, 0, deck - lands);
-        let res0 = turn0(deck0, cmc - 1, |hand: ColoredPile| {
-            hand.colored() >= colored_mana && hand.lands() >= cmc
-        });
-        let deck1 = ColoredPile::new(colored, lands - colored, deck - lands);
-        let res1 = turn0(deck1, cmc - 1, |hand: ColoredPile| {
-            hand.colored() >= colored_mana && hand.lands() >= cmc
-        });
-        format!("{:.1}%", res1 / res0 * 100.0)
+    fn apply<P>(s: State<P>, effects: &[Effect], fx: &Effects<P>) -> Vec<State<P>>
+    where
+        P: Pile + LandPile + Clone + Add<Output = P> + Sub<Output = P>,
+    {
+        effects.iter().fold(vec![s], |states, &effect| {
+            merge(states
+                .into_iter()
+                .flat_map(|s| match effect {
+                    Effect::Draw(n) => (0..n).fold(vec![s], |states, _| {
+                        states
+                            .into_iter()
+                            .flat_map(take)
+                            .map(|(mut s, card)| {
+                                if let Some(card) = card {
+                                    s.board.hand = s.board.hand.clone() + card
+                                }
+                                s
+                            })
+                            .collect()
+                    }),
+                    Effect::Scry(n) | Effect::Surveil(n) => look(s, n, &*fx.look),
+                })
+                .collect())
+        })
     }
-}
-
-// ================================================================================
-
-pub mod dual {
-    use crate::pile::{DualPile, LandPile, Pile};
-    use crate::prob;
 
-    fn draw<G>(hand: DualPile, num_draws: usize, deck: DualPile, goal: G) -> f64
+    // Play a land if we haven't this turn, and do what it does.
+    fn land_drop<P>(s: State<P>, fx: &Effects<P>) -> Vec<State<P>>
     where
-        G: Fn(DualPile) -> bool,
+        P: Pile + LandPile + Clone + Add<Output = P> + Sub<Output = P>,
     {
-        if num_draws > 0 {
-            DualPile::foreach_possible(num_draws)
-                .filter(|draw| deck.has(draw) && goal(hand + *draw))
-                .map(|draw| deck.prob_draw(&draw))
-                .sum()
-        } else {
-            prob::cond(goal(hand))
+        let mut s = s;
+        let land = match s.board.hand.land_drop(&s.board.lands) {
+            Some(land) if !s.dropped => land,
+            _ => return vec![s],
+        };
+        s.board.hand = s.board.hand.clone() - land.clone();
+        s.board.lands = s.board.lands.clone() + land.clone();
+        s.dropped = true;
+        match fx.lands.iter().find(|l| key(&[&l.card]) == key(&[&land])) {
+            Some(l) => {
+                if !l.tapped {
+                    s.mana += 1
+                }
+                apply(s, &l.effects, fx)
+            }
+            None => {
+                s.mana += 1;
+                vec![s]
+            }
         }
     }
 
-    pub fn turn0<G>(deck: DualPile, num_draws: usize, goal: G) -> f64
+    // One turn with effects: draw, play a land, and cast the cantrips while
+    // we have the mana, playing a land from what they find if we haven't.
+    fn turn_with<P>(states: Vec<State<P>>, draw: bool, fx: &Effects<P>) -> Vec<State<P>>
     where
-        G: Fn(DualPile) -> bool,
+        P: Pile + LandPile + Clone + Add<Output = P> + Sub<Output = P>,
     {
-        let mut mull = 1.0;
-        let mut succ = 0.0;
-        //let mut tally = 0.0;
-
-        for hand_size in (4..=7).rev() {
-            let (lands_min, lands_max) = super::mull_rule(hand_size);
-
-            // Probability of keeping
-            let keep:f64 = (lands_min..=lands_max)
-                .map(|lands| deck.prob_land(lands, hand_size - lands))
-                .sum();
-
-            // Probability of casting
-            let cast: f64 = DualPile::foreach_possible(hand_size)
-                .filter(|hand| hand.lands() >= lands_min && hand.lands() <= lands_max)
-                .filter(|hand| deck.has(hand))
-                .map(|hand| deck.prob_draw(&hand) * draw(hand, num_draws, deck - hand, |g| goal(g)))
-                .sum();
-
-            succ += mull * (cast * keep);
-            mull *= 1.0 - keep;
+        let drawn = if draw { states.into_iter().flat_map(|s| apply(s, &[Effect::Draw(1)], fx)).collect() } else { states };
+        let mut states = merge(drawn.into_iter()
+            .flat_map(|mut s| {
+                s.mana = s.board.lands.lands();
+                s.dropped = false;
+                land_drop(s, fx)
+            })
+            .collect());
+
+        // Cast a cantrip in each state that can, until none can.
+        let mut done = vec![];
+        while !states.is_empty() {
+            let mut next = vec![];
+            for mut s in states {
+                let castable = fx.cantrips.iter().find(|c| s.board.hand.has(&c.card) && c.cost <= s.mana && (c.colors)(&s.board.lands));
+                match castable {
+                    Some(c) => {
+                        s.board.hand = s.board.hand.clone() - c.card.clone();
+                        s.mana -= c.cost;
+                        next.extend(apply(s, &c.effects, fx).into_iter().flat_map(|s| land_drop(s, fx)))
+                    }
+                    None => {
+                        s.mana = 0;
+                        s.dropped = false;
+                        done.push(s)
+                    }
+                }
+            }
+            states = merge(next)
         }
 
-        succ
+        merge(done)
     }
 
-    pub fn cards<G>(
-        lands: usize,
-        deck: usize,
-        uncolored: usize,
-        a_rate: f64,
-        num_draws: usize,
-        perc: f64,
-        goal: G,
-    ) -> i32
+    // The chance of reaching `goal` at the end of turn `turn`, from a kept
+    // `hand` and the `library`.
+    fn play_out<P, G>(hand: P, library: &P, turn: usize, play: PlayDraw, goal: &G) -> f64
     where
-        G: Fn(DualPile) -> bool,
+        P: Pile + LandPile + Clone + Add<Output = P> + Sub<Output = P>,
+        G: Fn(&Board<P>) -> bool,
     {
-        let deck0 = DualPile::new(0, 0, lands, 0, deck - lands);
-        let deck1 = DualPile::new(0, 0, lands - uncolored, uncolored, deck - lands);
-
-        let r0 = turn0(deck0, num_draws, |g| goal(g));
-        let r1 = turn0(deck1, num_draws, |g| goal(g));
-
-        if r1 < perc * r0 {
-            return -1;
-        }
-
-        for ab in 0..=lands {
-            let mono = lands - ab - uncolored;
-            let a = ((mono as f64) * a_rate + 0.5).round() as usize;
-            let b = mono - a;
-
-            assert!(a + b + ab + uncolored + (deck - lands) == deck);
+        let none = hand.clone() - hand.clone();
+        let mut states = vec![(1.0, library.clone(), Board { lands: none, hand })];
+
+        for t in 1..=turn {
+            if t > 1 || play == PlayDraw::OnTheDraw {
+                // Hands that come out the same are merged.
+                let mut next: BTreeMap<Vec<usize>, (f64, P, Board<P>)> = BTreeMap::new();
+                for (p, library, board) in states {
+                    for card in library.subsets(1) {
+                        let q = p * library.prob_draw(&card);
+                        let library = library.clone() - card.clone();
+                        let hand = board.hand.clone() + card;
+                        let k = key(&[&library, &hand, &board.lands]);
+                        match next.get_mut(&k) {
+                            Some(e) => e.0 += q,
+                            None => {
+                                next.insert(k, (q, library, Board { lands: board.lands.clone(), hand }));
+                            }
+                        }
+                    }
+                }
+                states = next.into_iter().map(|(_, v)| v).collect();
+            }
 
-            let deck0 = DualPile::new(a, b, ab, uncolored, deck - lands);
-            let r = turn0(deck0, num_draws, |g| goal(g));
-            if r >= perc * r0 {
-                return ab as i32;
+            for state in states.iter_mut() {
+                let board = &mut state.2;
+                if let Some(land) = board.hand.land_drop(&board.lands) {
+                    board.hand = board.hand.clone() - land.clone();
+                    board.lands = board.lands.clone() + land;
+                }
             }
         }
-        return -1;
-    }
-}
-
-// ================================================================================
 
-mod gen {
-    use crate::pile::{GenPile, LandPile, Pile};
-    use crate::prob;
+        states.iter().filter(|s| goal(&s.2)).map(|s| s.0).sum()
+    }
 
-    fn draw<G>(hand: GenPile, num_draws: usize, deck: GenPile, goal: G) -> f64
+    pub fn turn0<P, G>(deck: &P, turn: usize, play: PlayDraw, goal: G) -> f64
     where
-        G: Fn(GenPile) -> bool,
+        P: Pile + LandPile + Clone + Add<Output = P> + Sub<Output = P>,
+        G: Fn(&Board<P>) -> bool,
     {
-        if num_draws > 0 {
-            deck.subsets(num_draws)
-                .filter(|draw| goal(hand.clone() + draw.clone()))
-                .map(|draw| deck.prob_draw(&draw))
-                .sum()
-        } else {
-            prob::cond(goal(hand))
-        }
+        turn0_with(deck, turn, play, Rules::standard(), &LandWindow, goal)
     }
 
-    pub fn turn0<G>(deck: GenPile, num_draws: usize, goal: G) -> f64
+    pub fn turn0_with<P, G>(
+        deck: &P,
+        turn: usize,
+        play: PlayDraw,
+        rules: Rules,
+        policy: &dyn MulliganPolicy<P>,
+        goal: G,
+    ) -> f64
     where
-        G: Fn(GenPile) -> bool,
+        P: Pile + LandPile + Clone + Add<Output = P> + Sub<Output = P>,
+        G: Fn(&Board<P>) -> bool,
     {
-        let mut mull = 1.0;
-        let mut succ = 0.0;
-
-        for hand_size in (4..=7).rev() {
-            let (lands_min, lands_max) = super::mull_rule(hand_size);
-
-            // Probability of keeping
-            let keep = (lands_min..=lands_max)
-                .map(|lands| deck.prob_land(lands, hand_size - lands))
-                .sum::<f64>();
-
-            // Probability of casting
-            let cast: f64 = deck
-                .subsets(hand_size)
-                .filter(|hand| hand.lands() >= lands_min && hand.lands() <= lands_max)
-                .map(|hand| {
-                    let d0 = deck.prob_draw(&hand);
-                    let p0 = {
-                        let r = deck.clone() - hand.clone();
-                        draw(hand.clone(), num_draws, r, |g| goal(g))
-                    };
-                    d0 * p0
-                })
-                .sum();
-
-            succ += mull * cast;
-            mull *= 1.0 - keep;
-        }
+        super::success(&super::tries(deck, rules, policy, &|kept, rest| play_out(kept, rest, turn, play, &goal)))
+    }
 
-        succ
+    /// `turn0_with`, with the cantrips, scrying and surveilling of `effects`.
+    pub fn with_effects<P, G>(
+        deck: &P,
+        turn: usize,
+        play: PlayDraw,
+        rules: Rules,
+        policy: &dyn MulliganPolicy<P>,
+        effects: &Effects<P>,
+        goal: G,
+    ) -> f64
+    where
+        P: Pile + LandPile + Clone + Add<Output = P> + Sub<Output = P>,
+        G: Fn(&Board<P>) -> bool,
+    {
+        // The states of the kept hands merge as the turns go by, so we play
+        // them out together rather than one by one. We pick the cards to
+        // bottom as if the effects did nothing.
+        let reach = |kept: P, rest: &P| play_out(kept, rest, turn, play, &goal);
+        let tries: Vec<(f64, f64)> = super::kept(deck, rules, policy, &reach)
+            .into_iter()
+            .map(|hands| {
+                let keep = hands.iter().map(|h| h.0).sum();
+                let mut states = merge(hands
+                    .into_iter()
+                    .map(|(p, kept, library)| {
+                        let board = Board { lands: kept.clone() - kept.clone(), hand: kept };
+                        State { p, library, top: vec![], board, mana: 0, dropped: false }
+                    })
+                    .collect());
+                for t in 1..=turn {
+                    states = turn_with(states, t > 1 || play == PlayDraw::OnTheDraw, effects);
+                }
+                (keep, states.iter().filter(|s| goal(&s.board)).map(|s| s.p).sum())
+            })
+            .collect();
+        super::success(&tries)
     }
 }
 
@@ -266,18 +1535,270 @@ mod tests {
 
     use self::test::Bencher;
     use super::*;
+    use crate::pile::GenPileKeys;
 
     #[bench]
     pub fn b_minc(bh: &mut Bencher) {
         let l = 28;
         let d = 60;
-        let num_draws = 3;
+        let turn = 4;
         let pc = 0.9;
 
         let goal = |hand: ColoredPile| {
             hand.colored() >= 3 && hand.lands() >= 3 /*&& hand.turn >= 3*/
         };
-        bh.iter(|| single::cards(l, d, num_draws, pc, |h| goal(h)))
+        bh.iter(|| single::cards(l, d, turn, PlayDraw::OnThePlay, pc, Rules::classic(), |h| goal(h)))
+    }
+
+    #[test]
+    pub fn policy() {
+        let mut cats = Categories::new();
+        let land = cats.land("Mountain", &[Color::R]);
+        let one = cats.spell("One-drop", 1, &[Color::R]);
+        let other = cats.spell("Other", 3, &[Color::R]);
+        let deck = cats.pile(&[(land, 20), (one, 16), (other, 24)]);
+        let play = PlayDraw::OnThePlay;
+
+        // The aggro keep: a one-lander with three one-drops.
+        let aggro = |hand: &GenPile, mulls: usize| {
+            if mulls == 0 && hand[land] == 1 && hand[one] >= 3 {
+                Some(hand.clone())
+            } else {
+                LandWindow.keep(hand, mulls)
+            }
+        };
+        let both = |turn: usize, goal: &dyn Fn(GenPile) -> bool| {
+            (chance(&deck, turn, play, Rules::standard(), &LandWindow, goal),
+             chance(&deck, turn, play, Rules::standard(), &aggro, goal))
+        };
+
+        // More often a one-drop on turn 1, less often a land drop on turn 2.
+        let (window, keep) = both(1, &|h| h[land] >= 1 && h[one] >= 1);
+        assert!(keep > window + 1e-3, "{} vs {}", keep, window);
+        let (window, keep) = both(2, &|h| h.lands() >= 2);
+        assert!(keep < window - 1e-3, "{} vs {}", keep, window);
+    }
+
+    #[test]
+    pub fn london() {
+        let deck = ColoredPile::new(10, 7, 23);
+        let play = PlayDraw::OnThePlay;
+        let all = single::turn0(deck, 3, play, |_| true);
+        assert!((all - 1.0).abs() < 1e-9);
+
+        let goal = |hand: ColoredPile| hand.colored() >= 1 && hand.lands() >= 3;
+        let london = single::turn0(deck, 3, play, goal);
+        let classic = single::turn0_with(deck, 3, play, Rules::classic(), &LandWindow, goal);
+        assert!(london > 0.5 && london < 1.0);
+        assert!(london != classic);
+
+        // With one land in ten cards, 7 and 6 card hands are never kept. To
+        // 5 we keep the 7 cards with the land, 7 times in 10, and to 4 we
+        // keep whatever we get: 0.7 + 0.3 * 0.7 = 0.91.
+        let one = ColoredPile::new(1, 0, 9);
+        assert!((single::turn0(one, 1, play, |h| h.lands() >= 1) - 0.91).abs() < 1e-12);
+
+        // Only the first 7 cards count.
+        let keep7 = |hand: &ColoredPile, mulls: usize| if mulls == 0 { Some(*hand) } else { None };
+        let all = single::turn0_with(deck, 1, play, Rules::standard(), &keep7, |h| h.lands() == 3);
+        assert!((all - deck.prob_land(3, 4)).abs() < 1e-9);
+
+        // The best policy does at least as well as any, and is a policy itself.
+        let best = optimal::solve(&deck, 3, play, Rules::standard(), goal);
+        assert!(best.prob >= london - 1e-9);
+        let again = single::turn0_with(deck, 3, play, Rules::standard(), &best, goal);
+        assert!((again - best.prob).abs() < 1e-9);
+
+        // One more card can only help.
+        assert!(single::turn0(deck, 3, PlayDraw::OnTheDraw, goal) > london);
+    }
+
+    #[test]
+    pub fn dual_lands() {
+        // A deck of duals is a deck of colored lands, with either rule.
+        let duals = DualPile::new(0, 0, 17, 0, 23);
+        let colored = ColoredPile::new(17, 0, 23);
+        let play = PlayDraw::OnThePlay;
+        for &rules in &[Rules::standard(), Rules::classic()] {
+            let d = dual::turn0_with(duals, 3, play, rules, &LandWindow, |h: DualPile| h.ab >= 2 && h.lands() >= 3);
+            let s = single::turn0_with(colored, 3, play, rules, &LandWindow, |h: ColoredPile| h.colored() >= 2 && h.lands() >= 3);
+            assert!((d - s).abs() < 1e-12, "{:?}: {} vs {}", rules.mulligan, d, s);
+        }
+        // As the published tables have it with the classic rule.
+        let classic: f64 = table_chance(&duals, 3, play, Rules::classic(), |h: DualPile| h.lands() >= 3);
+        assert!((classic - 0.7914658314538748).abs() < 1e-9, "{}", classic);
+    }
+
+    #[test]
+    pub fn arena() {
+        let deck = ColoredPile::new(10, 7, 23);
+        let play = PlayDraw::OnThePlay;
+        let first7 = |hand: &ColoredPile, mulls: usize| if mulls == 0 { Some(*hand) } else { None };
+        let chance = |opening, lands| {
+            let rules = Rules::new(Mulligan::London, opening);
+            single::turn0_with(deck, 1, play, rules, &first7, |h: ColoredPile| h.lands() == lands)
+        };
+
+        // One candidate hand is a plain shuffle, and the chances add up to 1.
+        for &opening in &[Opening::Arena(1), Opening::Arena(2), Opening::Arena(3)] {
+            let all: f64 = (0..=7).map(|l| chance(opening, l)).sum();
+            assert!((all - 1.0).abs() < 1e-9);
+        }
+        assert!((chance(Opening::Arena(1), 3) - chance(Opening::Shuffle, 3)).abs() < 1e-9);
+
+        // 3 lands is closest to 7 * 17 / 40 = 2.975.
+        assert!(chance(Opening::Arena(2), 3) > chance(Opening::Shuffle, 3));
+        assert!(chance(Opening::Arena(2), 0) < chance(Opening::Shuffle, 0));
+
+        // Only the first hand is smoothed: after a mulligan to 6 the hand is
+        // dealt as with a plain shuffle.
+        let mull_to_6 = |hand: &ColoredPile, mulls: usize| if mulls == 1 { LandWindow.keep(hand, 1) } else { None };
+        for lands in 0..=6 {
+            let goal = |h: ColoredPile| h.lands() == lands;
+            let arena = single::turn0_with(deck, 1, play, Rules::arena(), &mull_to_6, goal);
+            assert!((arena - single::turn0_with(deck, 1, play, Rules::standard(), &mull_to_6, goal)).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    pub fn lands_in_play() {
+        let deck = ColoredPile::new(10, 7, 23);
+        let play = PlayDraw::OnThePlay;
+
+        // Lands seen and lands in play agree on "`cmc` lands by turn `cmc`".
+        let seen = single::turn0(deck, 3, play, |h| h.colored() >= 2 && h.lands() >= 3);
+        let in_play = battlefield::turn0(&deck, 3, play, |b| b.lands.colored() >= 2 && b.lands.lands() >= 3);
+        assert!((seen - in_play).abs() < 1e-9);
+
+        // But we only play one land a turn.
+        assert_eq!(battlefield::turn0(&deck, 1, play, |b| b.lands.lands() >= 2), 0.0);
+        assert!(single::turn0(deck, 1, play, |h| h.lands() >= 2) > 0.9);
+    }
+
+    #[test]
+    pub fn explain() {
+        let deck = ColoredPile::new(10, 7, 23);
+        let play = PlayDraw::OnThePlay;
+        let goal = |hand: ColoredPile| hand.colored() >= 2 && hand.lands() >= 3;
+        let ex = explain::explain(&deck, 3, play, Rules::standard(), &LandWindow, goal, explain::colored);
+
+        // The misses add up to the chance of missing, most likely first, and
+        // all of them miss.
+        assert!((ex.prob - (1.0 - single::turn0(deck, 3, play, goal))).abs() < 1e-9);
+        assert!(ex.misses.windows(2).all(|w| w[0].1 >= w[1].1));
+        assert!(ex.misses.iter().all(|m| !m.0.starts_with("3 lands, 2") && !m.0.contains("4 lands, 2")));
+        let shares: f64 = (0..ex.misses.len()).map(|i| ex.share(i)).sum();
+        assert!((shares - 1.0).abs() < 1e-9);
+        assert!(ex.describe(1)[0].contains("% of failures: "));
+
+        // A policy that never keeps misses every time.
+        let never = |_: &ColoredPile, _: usize| None;
+        let ex = explain::explain(&deck, 3, play, Rules::standard(), &never, goal, explain::colored);
+        assert_eq!(ex.misses.len(), 1);
+        assert!((ex.prob - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    pub fn key_order() {
+        // The same deck, with its kinds of cards declared in either order.
+        let chance = |reversed: bool| {
+            let mut cats = Categories::new();
+            let (a, b, spell) = if reversed {
+                let spell = cats.spell("Spell", 3, &[Color::W]);
+                let b = cats.land("B", &[Color::U]);
+                (cats.land("A", &[Color::W]), b, spell)
+            } else {
+                let a = cats.land("A", &[Color::W]);
+                let b = cats.land("B", &[Color::U]);
+                (a, b, cats.spell("Spell", 3, &[Color::W]))
+            };
+            let deck = cats.pile(&[(a, 6), (b, 11), (spell, 23)]);
+            let goal = |h: GenPile| h[a] >= 2 && h.lands() >= 3;
+            gen::turn0_with(deck, 3, PlayDraw::OnTheDraw, Rules::standard(), &LandWindow, goal)
+        };
+        assert!((chance(false) - chance(true)).abs() < 1e-12, "{} vs {}", chance(false), chance(true));
+    }
+
+    #[test]
+    pub fn convolution() {
+        fn is_land(idx: usize) -> bool {
+            idx < 2
+        }
+        let deck = GenPile::new(vec![9, 8, 4, 6, 13], GenPileKeys::new(5, is_land));
+        let goal = |h: GenPile| h[0] >= 1 && h.lands() >= 3 && h[2] + h[3] > 0;
+
+        // To the bit, as with the plain enumeration.
+        for &rules in &[Rules::standard(), Rules::classic(), Rules::arena()] {
+            for &(turn, play) in &[(1, PlayDraw::OnThePlay), (3, PlayDraw::OnTheDraw)] {
+                let tries = tries(&deck, rules, &LandWindow, &|kept, rest| draw(kept, play.draws(turn), rest, &goal));
+                let plain: f64 = success(&tries);
+                assert_eq!(gen::turn0_with(deck.clone(), turn, play, rules, &LandWindow, goal), plain);
+            }
+        }
+
+        // The same as `single` with one color, with either rule.
+        let deck = GenPile::new(vec![10, 7, 23], GenPileKeys::new(3, is_land));
+        let colored = ColoredPile::new(10, 7, 23);
+        for &rules in &[Rules::standard(), Rules::classic()] {
+            let g = gen::turn0_with(deck.clone(), 3, PlayDraw::OnThePlay, rules, &LandWindow, |h| h[0] >= 2 && h.lands() >= 3);
+            let s = single::turn0_with(colored, 3, PlayDraw::OnThePlay, rules, &LandWindow, |h| h.colored() >= 2 && h.lands() >= 3);
+            assert!((g - s).abs() < 1e-12, "{:?}: {} vs {}", rules.mulligan, g, s);
+        }
+    }
+
+    #[test]
+    pub fn exact() {
+        let deck = ColoredPile::new(10, 7, 23);
+        let play = PlayDraw::OnThePlay;
+        let first7 = |hand: &ColoredPile, mulls: usize| if mulls == 0 { Some(*hand) } else { None };
+        let rules = Rules::arena();
+
+        // Exactly 1, not just close to it.
+        let all = (0..=7).fold(BigRational::zero(), |s, l| {
+            s + single::turn0_as::<_, BigRational>(deck, 1, play, rules, &first7, |h: ColoredPile| h.lands() == l)
+        });
+        assert_eq!(all, BigRational::one());
+
+        let goal = |h: ColoredPile| h.colored() >= 2 && h.lands() >= 3;
+        for &rules in &[Rules::standard(), Rules::classic(), Rules::arena()] {
+            let float = single::turn0_with(deck, 3, play, rules, &LandWindow, goal);
+            let exact: BigRational = single::turn0_as(deck, 3, play, rules, &LandWindow, goal);
+            assert!((exact.to_f64() - float).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    pub fn multi() {
+        use crate::colors::Color::{B, C, R, U};
+        use crate::mana::Mana;
+        use crate::pile::ManaPile;
+
+        // With two colors, the same as the dual model.
+        let play = PlayDraw::OnThePlay;
+        for &(a, b, ab, x) in &[(7, 5, 4, 1), (9, 8, 0, 0), (2, 3, 10, 2)] {
+            let dual_deck = DualPile::new(a, b, ab, x, 23);
+            let multi_deck = ManaPile::new(&[U, B], &[(&[U], a), (&[B], b), (&[U, B], ab), (&[C], x)], 23);
+            for &(amana, bmana, cmc) in &[(1, 1, 2), (2, 1, 3), (1, 0, 4)] {
+                let goal = |hand: DualPile| {
+                    let a_left = if amana > hand.a { amana - hand.a } else { 0 };
+                    let b_left = if bmana > hand.b { bmana - hand.b } else { 0 };
+                    a_left + b_left <= hand.ab && hand.lands() >= cmc
+                };
+                let cost = Mana::u(amana as u32) + Mana::b(bmana as u32) + Mana::n((cmc - amana - bmana) as u32);
+                for &rules in &[Rules::standard(), Rules::classic()] {
+                    let d = dual::turn0_with(dual_deck, cmc, play, rules, &LandWindow, goal);
+                    let m = multi::castable(multi_deck.clone(), &cost, cmc, play, rules);
+                    assert!((d - m).abs() < 1e-12, "{:?} {}: {} vs {}", multi_deck, cost.pretty(), d, m);
+                }
+            }
+        }
+
+        // A tri land helps with three colors more than a mono land does.
+        let grixis = |tri: usize| ManaPile::new(&[U, B, R], &[(&[U], 6), (&[B], 5), (&[R], 6 - tri), (&[U, B, R], tri)], 23);
+        let cost = Mana::parse("{U}{B}{R}");
+        let (without, with) = (multi::castable(grixis(0), &cost, 3, play, Rules::standard()),
+                               multi::castable(grixis(4), &cost, 3, play, Rules::standard()));
+        assert!(0.0 < without && without < with && with < 1.0);
     }
 }
 
@@ -288,64 +1809,79 @@ fn pm2(a: usize, b: usize, c: usize) -> String {
     res
 }
 
+fn opening_note(rules: Rules) -> String {
+    match rules.opening {
+        Opening::Shuffle => "".to_string(),
+        o => format!(", {}", o.name()),
+    }
+}
+
 // Summary of [lands] lands in a [D] card deck
-pub fn summary(lands: usize, deck: usize, uncolored_lands: usize) {
-    let mut table = Table::new(5, 9);
+pub fn summary(lands: usize, deck: usize, uncolored_lands: usize, rules: Rules) {
+    for play in PlayDraw::both() {
+        let mut table = Table::new(5, 9);
 
-    {
-        table.set(
-            0,
-            0,
-            LStr(format!("{}/{}({})", lands, deck, uncolored_lands)),
-        );
-        table.set(0, 1, RStr("--".to_string()));
-        for cless in 1u32..=7 {
-            table.set(0, 1 + cless as usize, U32(cless))
+        {
+            table.set(
+                0,
+                0,
+                LStr(format!("{}/{}({})", lands, deck, uncolored_lands)),
+            );
+            table.set(0, 1, RStr("--".to_string()));
+            for cless in 1u32..=7 {
+                table.set(0, 1 + cless as usize, U32(cless))
+            }
         }
-    }
 
-    for cmana in 2..=5 {
-        for bmana in 1..=cmana / 2 {
-            let amana = cmana - bmana;
+        let mut cells = vec![];
+        for cmana in 2..=5 {
+            for bmana in 1..=cmana / 2 {
+                let amana = cmana - bmana;
 
-            let gstr = pm2(amana, bmana, cmana - amana - bmana);
-            table.set(cmana - 1, 0, RStr(gstr));
+                let gstr = pm2(amana, bmana, cmana - amana - bmana);
+                table.set(cmana - 1, 0, RStr(gstr));
 
-            for cless in 0..=7 {
-                let arate = (amana as f64) / (amana + bmana) as f64;
-                let cmc = cmana + cless;
-                let num_draws = cmc - 1;
-                let goal = |hand: DualPile| {
-                    let a_left = if amana > hand.a { amana - hand.a } else { 0 };
-                    let b_left = if bmana > hand.b { bmana - hand.b } else { 0 };
+                for cless in 0..=7 {
+                    cells.push((cmana, amana, bmana, cless))
+                }
+            }
+        }
 
-                    let ok = (a_left + b_left) <= hand.ab && hand.lands() >= cmc; // enough lands for cmc
+        let results = par::map(&cells, |&(cmana, amana, bmana, cless)| {
+            let arate = (amana as f64) / (amana + bmana) as f64;
+            let cmc = cmana + cless;
+            let goal = |hand: DualPile| {
+                let a_left = if amana > hand.a { amana - hand.a } else { 0 };
+                let b_left = if bmana > hand.b { bmana - hand.b } else { 0 };
 
-                    //let gstr = pm2(amana, bmana, cmana - amana - bmana);
-                    //if ok { println!("{}/{}: {}\n", gstr, num_draws, hand) };
-                    ok
-                };
-                let res = dual::cards(lands, deck, uncolored_lands, arate, num_draws, 0.90, goal);
+                let ok = (a_left + b_left) <= hand.ab && hand.lands() >= cmc; // enough lands for cmc
 
-                table.set(
-                    cmana - 1,
-                    cless + 1,
-                    if res == 0 {
-                        Empty
-                    }
-                    //else if res == (lands - uncolored_lands) as int { RStr("**") }
-                    else if res == -1 {
-                        RStr("**".to_string())
-                    } else {
-                        I32(res)
-                    },
-                )
-            }
+                //let gstr = pm2(amana, bmana, cmana - amana - bmana);
+                //if ok { println!("{}/{}: {}\n", gstr, cmc, hand) };
+                ok
+            };
+            dual::cards(lands, deck, uncolored_lands, arate, cmc, play, 0.90, rules, goal)
+        });
+
+        for (&(cmana, _, _, cless), &res) in cells.iter().zip(results.iter()) {
+            table.set(
+                cmana - 1,
+                cless + 1,
+                if res == 0 {
+                    Empty
+                }
+                //else if res == (lands - uncolored_lands) as int { RStr("**") }
+                else if res == -1 {
+                    RStr("**".to_string())
+                } else {
+                    I32(res)
+                },
+            )
         }
-    }
 
-    println!("");
-    table.print(&format!("{} lands, {} colorless", lands, uncolored_lands));
+        println!("");
+        table.print(&format!("{} lands, {} colorless, {}{}", lands, uncolored_lands, play.name(), opening_note(rules)));
+    }
 }
 
 fn pm(colored_mana: usize, cmc: usize) -> String {
@@ -359,32 +1895,44 @@ fn pm(colored_mana: usize, cmc: usize) -> String {
     res
 }
 
-pub fn summary_c(lands: usize, deck: usize) {
-    // Making my adjusted tables
-    let mut table = Table::new(5, 9);
+// The (colored mana, colorless mana) cells of a summary table, row by row.
+fn cells(colored: usize, colorless: usize) -> Vec<(usize, usize)> {
+    (1..=colored).flat_map(|cmana| (0..=colorless).map(move |cless| (cmana, cless))).collect()
+}
 
-    {
-        table.set(0, 0, LStr(format!("{}/{}", lands, deck)));
-        table.set(0, 1, RStr("--".to_string()));
-        for cless in 1..=7 {
-            table.set(0, (1 + cless) as usize, I32(cless))
+// The goals count the lands seen, which here is the same as the lands in play
+// (see `battlefield`) and much faster.
+pub fn summary_c(lands: usize, deck: usize, rules: Rules) {
+    for play in PlayDraw::both() {
+        // Making my adjusted tables
+        let mut table = Table::new(5, 9);
+
+        {
+            table.set(0, 0, LStr(format!("{}/{}", lands, deck)));
+            table.set(0, 1, RStr("--".to_string()));
+            for cless in 1..=7 {
+                table.set(0, (1 + cless) as usize, I32(cless))
+            }
         }
-    }
 
-    for cmana in 1..=4 {
-        let gstr = pm(cmana, cmana);
-        table.set(cmana, 0, RStr(gstr));
+        for cmana in 1..=4 {
+            let gstr = pm(cmana, cmana);
+            table.set(cmana, 0, RStr(gstr));
+        }
 
-        for cless in 0..=7 {
+        let cells = cells(4, 7);
+        let results = par::map(&cells, |&(cmana, cless)| {
             let cmc = cmana + cless;
-            let num_draws = cmc - 1;
             let goal = |hand: ColoredPile| {
                 let ok = hand.colored() >= cmana // colors okay
                     && hand.lands() >= cmc; // enough lands for cmc
 
                 ok
             };
-            let res = single::cards(lands, deck, num_draws, 0.90, goal);
+            single::cards(lands, deck, cmc, play, 0.90, rules, goal)
+        });
+
+        for (&(cmana, cless), &res) in cells.iter().zip(results.iter()) {
             table.set(
                 cmana,
                 1 + cless,
@@ -399,37 +1947,132 @@ pub fn summary_c(lands: usize, deck: usize) {
                 },
             )
         }
-    }
 
-    println!("");
-    table.print(&format!("{} lands", lands));
+        println!("");
+        table.print(&format!("{} lands, {}{}", lands, play.name(), opening_note(rules)));
+    }
 }
 
-pub fn summary_perc(lands: usize, colored_lands: usize, deck: usize) {
-    // Making my adjusted tables
-    let mut table = Table::new(5, 9);
+// `summary_c` in exact arithmetic. A cell where rounding moved the threshold
+// is marked with `*`, and a cell where the ratio is exactly 90% with `=`;
+// both are explained under the table.
+pub fn summary_c_exact(lands: usize, deck: usize, rules: Rules) {
+    for play in PlayDraw::both() {
+        let mut table = Table::new(5, 9);
+        let mut notes = vec![];
 
-    {
         table.set(0, 0, LStr(format!("{}/{}", lands, deck)));
         table.set(0, 1, RStr("--".to_string()));
         for cless in 1..=7 {
             table.set(0, (1 + cless) as usize, I32(cless))
         }
-    }
 
-    for cmana in 1..=4 {
-        let gstr = pm(cmana, cmana);
-        table.set(cmana, 0, RStr(gstr));
+        for cmana in 1..=4 {
+            table.set(cmana, 0, RStr(pm(cmana, cmana)));
+        }
+
+        let cells = cells(4, 7);
+        let results = par::map(&cells, |&(cmana, cless)| {
+            let cmc = cmana + cless;
+            let goal = |hand: ColoredPile| hand.colored() >= cmana && hand.lands() >= cmc;
+            let (res, ratio) = single::cards_as(lands, deck, cmc, play, BigRational::ratio(9, 10), rules, goal);
+            let (float, _) = single::cards_as(lands, deck, cmc, play, 0.90, rules, goal);
+            (res, ratio, float)
+        });
 
-        for cless in 0..=7 {
+        let perc = BigRational::ratio(9, 10);
+        for (&(cmana, cless), (res, ratio, float)) in cells.iter().zip(results.into_iter()) {
             let cmc = cmana + cless;
-            let res = single::prob_color_screwed(lands, colored_lands, deck, cmc, cmana);
+            let mark = if res != float {
+                notes.push(format!(
+                    "{}: {} exactly, {} in floating point; the ratio at {} is {} ({:.15})",
+                    pm(cmana, cmc), res, float, res, ratio, ratio.to_f64()
+                ));
+                "*"
+            } else if ratio == perc {
+                notes.push(format!("{}: the ratio at {} is exactly 9/10", pm(cmana, cmc), res));
+                "="
+            } else {
+                ""
+            };
+            table.set(cmana, 1 + cless, if res == 0 { Empty } else { RStr(format!("{}{}", res, mark)) })
+        }
+
+        println!("");
+        table.print(&format!("{} lands, {}{}, exact", lands, play.name(), opening_note(rules)));
+        for note in notes {
+            println!("{}", note)
+        }
+    }
+}
+
+pub fn summary_perc(lands: usize, colored_lands: usize, deck: usize, rules: Rules) {
+    for play in PlayDraw::both() {
+        // Making my adjusted tables
+        let mut table = Table::new(5, 9);
+
+        {
+            table.set(0, 0, LStr(format!("{}/{}", lands, deck)));
+            table.set(0, 1, RStr("--".to_string()));
+            for cless in 1..=7 {
+                table.set(0, (1 + cless) as usize, I32(cless))
+            }
+        }
+
+        for cmana in 1..=4 {
+            let gstr = pm(cmana, cmana);
+            table.set(cmana, 0, RStr(gstr));
+        }
+
+        let cells = cells(4, 7);
+        let results = par::map(&cells, |&(cmana, cless)| {
+            single::prob_color_screwed(lands, colored_lands, deck, cmana + cless, cmana, play, rules)
+        });
+        for (&(cmana, cless), res) in cells.iter().zip(results.into_iter()) {
             table.set(cmana, 1 + cless, RStr(res))
         }
+
+        println!("");
+        table.print(&format!("{}/{} lands, {}{}", colored_lands, lands, play.name(), opening_note(rules)));
+    }
+}
+
+// How much each goal gains from being on the draw, for a deck with `lands`
+// lands of which `colored` (for each color) produce the color.
+pub fn play_draw(lands: usize, colored: &[usize], deck: usize, rules: Rules) -> Table {
+    let mut goals: Vec<(String, usize, usize, usize)> = (1..=6)
+        .map(|turn| {
+            let name = format!("{} land{} on turn {}", turn, if turn == 1 { "" } else { "s" }, turn);
+            (name, lands, turn, 0)
+        })
+        .collect();
+    for &c in colored {
+        for cmc in 1..=3 {
+            goals.push((format!("{} with {} sources", pm(cmc, cmc), c), c, cmc, cmc));
+        }
+    }
+
+    let mut table = Table::new(1 + goals.len(), 4);
+    table.set(0, 0, LStr(format!("{}/{}", lands, deck)));
+    table.set(0, 1, RStr("Play".to_string()));
+    table.set(0, 2, RStr("Draw".to_string()));
+    table.set(0, 3, RStr("Gain".to_string()));
+
+    for (row, &(ref name, c, cmc, colored_mana)) in goals.iter().enumerate() {
+        let pile = ColoredPile::new(c, lands - c, deck - lands);
+        let goal = |hand: ColoredPile| hand.colored() >= colored_mana && hand.lands() >= cmc;
+        let p = PlayDraw::both()
+            .into_iter()
+            .map(|play| single::turn0_with(pile, cmc, play, rules, &LandWindow, goal))
+            .collect::<Vec<f64>>();
+
+        table.set(1 + row, 0, LStr(name.clone()));
+        table.set(1 + row, 1, RStr(format!("{:.1}%", p[0] * 100.0)));
+        table.set(1 + row, 2, RStr(format!("{:.1}%", p[1] * 100.0)));
+        table.set(1 + row, 3, RStr(format!("{:+.1}", (p[1] - p[0]) * 100.0)));
     }
 
-    println!("");
-    table.print(&format!("{}/{} lands", colored_lands, lands));
+    table
 }
 
 pub fn investigate() {
@@ -486,37 +2129,26 @@ pub fn investigate() {
     }
 
     {
-        static A: usize = 0;
-        static B: usize = 1;
-        static C: usize = 2;
-        static AB: usize = 3;
-        static BC: usize = 4;
-        static AC: usize = 5;
-        static S1: usize = 6;
-        static S2: usize = 7;
-        static O: usize = 8;
-
-        // a,b,c,ab,bc,ac,s,o
-        fn is_land(idx: usize) -> bool {
-            idx < 6
-        }
-
-        let info = GenPileKeys::new(9, is_land);
-
-        fn cc(hand: GenPile, a: usize, b: usize, x: usize) -> bool {
-            can_cast(
-                hand[A] + hand[AC],
-                hand[B] + hand[BC],
-                hand[AB],
-                hand[C],
-                a,
-                b,
-                x,
-            )
-        }
-
         let turn = 3;
 
+        // Two colors, A and B, and colorless lands C; spells S1 cost AA and
+        // S2 cost BB, plus generic mana to make `turn`.
+        let (a_color, b_color) = (Color::W, Color::U);
+        let mut cats = Categories::new();
+        let la = cats.land("A", &[a_color]);
+        let lb = cats.land("B", &[b_color]);
+        let lc = cats.land("C", &[Color::C]);
+        let lab = cats.land("AB", &[a_color, b_color]);
+        let lbc = cats.land("BC", &[b_color, Color::C]);
+        let lac = cats.land("AC", &[a_color, Color::C]);
+        let spell1 = cats.spell("S1", turn, &[a_color]);
+        let spell2 = cats.spell("S2", turn, &[b_color]);
+        let other = cats.spell("Other", 0, &[]);
+
+        let cc = |hand: GenPile, a: usize, b: usize, x: usize| {
+            can_cast(hand[la] + hand[lac], hand[lb] + hand[lbc], hand[lab], hand[lc], a, b, x)
+        };
+
         for cmc2s in 2..=16 {
             for s1 in 1..cmc2s {
                 let s2 = cmc2s - s1;
@@ -527,13 +2159,14 @@ pub fn investigate() {
                 for a in 0..=17 {
                     let b = 17 - a;
 
-                    let deck = GenPile::new(vec![a, b, 0, 0, 0, 0, s1, s2, 23 - s1 - s2], info);
-                    let p_base = gen::turn0(deck.clone(), turn, |hand: _| {
-                        hand.lands() >= turn && hand[S1] + hand[S2] > 0
+                    let deck = cats.pile(&[(la, a), (lb, b), (spell1, s1), (spell2, s2), (other, 23 - s1 - s2)]);
+                    let conv = gen::Convolution::new(&deck, turn, PlayDraw::OnTheDraw, Rules::standard());
+                    let p_base = conv.chance(&LandWindow, &|hand: GenPile| {
+                        hand.lands() >= turn && hand[spell1] + hand[spell2] > 0
                     });
-                    let p_succ = gen::turn0(deck, turn, |hand| {
-                        (cc(hand.clone(), 2, 0, turn - 2) && hand.clone()[S1] > 0)
-                            || (cc(hand.clone(), 0, 2, turn - 2) && hand[S2] > 0)
+                    let p_succ = conv.chance(&LandWindow, &|hand: GenPile| {
+                        (cc(hand.clone(), 2, 0, turn - 2) && hand.clone()[spell1] > 0)
+                            || (cc(hand.clone(), 0, 2, turn - 2) && hand[spell2] > 0)
                     });
 
                     let p_rel = p_succ / p_base;
@@ -559,8 +2192,8 @@ pub fn investigate() {
 }
 
 // Making the Frank 1 colored mana table:
-pub fn frank(colored_mana: usize, cmc: usize) -> Table {
-    let mut t1 = Table::new(4, 8);
+pub fn frank(colored_mana: usize, cmc: usize, rules: Rules) -> Table {
+    let mut t1 = Table::new(7, 8);
 
     let ps = pm(colored_mana, cmc);
 
@@ -573,15 +2206,16 @@ pub fn frank(colored_mana: usize, cmc: usize) -> Table {
     let manas = vec![16, 17, 18];
     let lines = manas
         .iter()
-        .map(|l| {
+        .flat_map(|l| {
             let f = 10.0f64;
-            (40, *l, 0, (f - 1.0) / f)
+            PlayDraw::both().into_iter().map(move |e| (40, *l, e, (f - 1.0) / f))
         })
         .enumerate();
 
+    let mut cells = vec![];
     for (line_no, line) in lines {
-        let (d, l, e, pc) = line;
-        let sym = if e == 0 { 'p' } else { 'd' };
+        let (_, l, e, _) = line;
+        let sym = if e == PlayDraw::OnThePlay { 'p' } else { 'd' };
         t1.set(
             1 + line_no,
             0,
@@ -589,43 +2223,48 @@ pub fn frank(colored_mana: usize, cmc: usize) -> Table {
         );
 
         for turn in 1..=7 {
-            let num_draws = turn - 1 + e;
-            let goal = |hand: ColoredPile| {
-                hand.colored() >= colored_mana // colors okay
-                    && hand.lands() >= cmc // enough lands for cmc
-                    && turn >= cmc // one land per turn
-            };
-            let res = single::cards(l, d, num_draws, pc, goal);
-            t1.set(1 + line_no, turn, if res == 0 { Empty } else { I32(res) })
+            cells.push((line_no, line, turn))
         }
     }
 
+    let results = par::map(&cells, |&(_, (d, l, e, pc), turn)| {
+        let goal = |hand: ColoredPile| {
+            hand.colored() >= colored_mana // colors okay
+                && hand.lands() >= cmc // enough lands for cmc
+                && turn >= cmc // one land per turn
+        };
+        single::cards(l, d, turn, e, pc, rules, goal)
+    });
+    for (&(line_no, _, turn), &res) in cells.iter().zip(results.iter()) {
+        t1.set(1 + line_no, turn, if res == 0 { Empty } else { I32(res) })
+    }
+
     t1
 }
 
-pub fn frank_table() {
-    frank(1, 1).print("");
-    frank(1, 2).print("");
-    frank(1, 3).print("");
-    frank(1, 4).print("");
-    frank(1, 5).print("");
-    frank(1, 6).print("");
-    frank(1, 7).print("");
+pub fn frank_table(rules: Rules) {
+    frank(1, 1, rules).print("");
+    frank(1, 2, rules).print("");
+    frank(1, 3, rules).print("");
+    frank(1, 4, rules).print("");
+    frank(1, 5, rules).print("");
+    frank(1, 6, rules).print("");
+    frank(1, 7, rules).print("");
     //
-    frank(2, 2).print("");
-    frank(2, 3).print("");
-    frank(2, 4).print("");
-    frank(2, 5).print("");
-    frank(2, 6).print("");
-    frank(2, 7).print("");
+    frank(2, 2, rules).print("");
+    frank(2, 3, rules).print("");
+    frank(2, 4, rules).print("");
+    frank(2, 5, rules).print("");
+    frank(2, 6, rules).print("");
+    frank(2, 7, rules).print("");
     //
-    frank(3, 3).print("");
-    frank(3, 4).print("");
-    frank(3, 5).print("");
-    frank(3, 6).print("");
-    frank(3, 7).print("");
+    frank(3, 3, rules).print("");
+    frank(3, 4, rules).print("");
+    frank(3, 5, rules).print("");
+    frank(3, 6, rules).print("");
+    frank(3, 7, rules).print("");
     //
-    frank(7, 7).print("");
+    frank(7, 7, rules).print("");
 }
 
 pub fn show_card_text(txt: &str, width: usize) {
@@ -1,6 +1,7 @@
 use rustc_serialize::{json};
 use crate::mana::Mana;
 use crate::colors::Color;
+use crate::names::Named;
 use std::io::Error;
 use url;
 use reqwest;
@@ -85,6 +86,7 @@ impl Rarity {
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Card {
     pub card_name   : String,
+    pub names       : Vec<String>, // all faces of split and double-faced cards
     pub mana_cost   : Mana,
     pub colors      : Vec<Color>,
     pub card_type   : String,
@@ -96,7 +98,19 @@ pub struct Card {
     pub card_text   : String,
     pub image_name  : String,
     pub expansion   : String,
-    pub rarity      : Rarity
+    pub rarity      : Rarity,
+    pub legalities  : Vec<(String, String)> // (format, legality), e.g. ("Modern", "Banned")
+}
+
+impl Named for Card {
+    fn names(&self) -> Vec<String> {
+        let mut res = vec![self.card_name.clone()];
+        // Only the front face answers to the full "Fire // Ice" name.
+        if self.names.len() > 1 && self.names[0] == self.card_name {
+            res.push(self.names.join(" // "))
+        }
+        res
+    }
 }
 
 pub fn fetch(set: &str) -> Result<json::Json, MtgError> {
@@ -137,6 +151,20 @@ pub fn fetch_set(set: &str) -> Vec<Card> {
         subtypes
     }
 
+    // Legalities are a list of `{ "format": .., "legality": .. }` in older
+    // MTGJSON files, and an object from format to legality in newer ones.
+    fn to_legalities(card : &json::Json) -> Vec<(String, String)> {
+        match card.find("legalities") {
+            Some(&json::Json::Array(ref ls)) => ls.iter()
+                .map(|l| (to_str(l.find("format")), to_str(l.find("legality"))))
+                .collect(),
+            Some(&json::Json::Object(ref ls)) => ls.iter()
+                .map(|(f, l)| (f.to_string(), to_str(Some(l))))
+                .collect(),
+            _ => vec![]
+        }
+    }
+
     match fetch(set) {
         Ok(doc) => {
             let cards = doc
@@ -148,6 +176,7 @@ pub fn fetch_set(set: &str) -> Vec<Card> {
                     //println!("{}", card.to_pretty_str());
 
                     let name = to_str(card.find("name"));
+                    let names = to_str_list(card, "names");
                     let typ  = to_str(card.find("type"));
                     let styps = to_str_list(card, "supertypes");
                     let typs = to_str_list(card, "types");
@@ -155,6 +184,7 @@ pub fn fetch_set(set: &str) -> Vec<Card> {
                     let image = to_str(card.find("imageName"));
                     let text  = to_str(card.find("text"));
                     let rarity:Rarity = Rarity::parse(&*to_str(card.find("rarity"))).unwrap(); //.unwrap_or(Rarity::Special);
+                    let legalities = to_legalities(card);
                     let power = to_str(card.find("power"));
                     let toughness = to_str(card.find("toughness"));
 
@@ -177,6 +207,7 @@ pub fn fetch_set(set: &str) -> Vec<Card> {
 
                     let c = Card {
                         card_name   : name,
+                        names       : names,
                         card_type   : typ,
                         super_types : styps,
                         card_types  : typs,
@@ -188,7 +219,8 @@ pub fn fetch_set(set: &str) -> Vec<Card> {
                         card_text   : text,
                         expansion   : set.to_string(),
                         colors      : colors,
-                        rarity      : rarity
+                        rarity      : rarity,
+                        legalities  : legalities
                     };
 
                     c
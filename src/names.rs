@@ -0,0 +1,238 @@
+use std::fmt;
+
+//
+// Card name resolution.
+//
+// Names in deck lists are typed by hand, so they rarely match the database
+// exactly: "Aether Hub" versus "Æther Hub", "urborg tomb of yawgmoth", or
+// just one half of a split card. We first try an exact match, then a folded
+// match (case, diacritics and punctuation removed), and if that fails we
+// suggest the closest names by edit distance.
+//
+
+/// Anything that can be looked up by name.
+pub trait Named {
+    /// All the names this entry answers to. The first one is the canonical
+    /// name, which is the one used in suggestions.
+    fn names(&self) -> Vec<String>;
+}
+
+/// A name that did not resolve to exactly one entry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotFound {
+    pub query: String,
+    pub ambiguous: bool,
+    pub suggestions: Vec<String>,
+}
+
+impl fmt::Display for NotFound {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        if self.ambiguous {
+            write!(fmt, "Card name '{}' is ambiguous", self.query)?
+        } else {
+            write!(fmt, "Could not find card named '{}' in database", self.query)?
+        }
+        if !self.suggestions.is_empty() {
+            write!(fmt, " (did you mean {}?)", self.suggestions.iter()
+                   .map(|s| format!("'{}'", s))
+                   .collect::<Vec<String>>()
+                   .join(", "))?
+        }
+        Ok(())
+    }
+}
+
+// Number of suggestions we report when a name isn't found.
+const NUM_SUGGESTIONS: usize = 3;
+
+/// Fold a name for comparison: lower case, diacritics removed (so 'Æ'
+/// becomes "ae"), punctuation dropped and white-space collapsed. The faces
+/// of a split card are joined with " // " however they were separated.
+///
+/// Only the Latin-1 letters with diacritics are folded, from a table: we
+/// have no Unicode decomposition at hand, and card names rarely go beyond
+/// Latin-1. Other letters are only lower-cased, so "Ōkina" folds to "ōkina"
+/// and "Okina" is found by `suggest` rather than matched.
+pub fn fold(name: &str) -> String {
+    faces(name).iter()
+        .map(|f| fold_face(f))
+        .collect::<Vec<String>>()
+        .join(" // ")
+}
+
+fn fold_face(name: &str) -> String {
+    let mut res = String::with_capacity(name.len());
+    for ch in name.chars() {
+        match ch {
+            'Æ' | 'æ' => res.push_str("ae"),
+            'Œ' | 'œ' => res.push_str("oe"),
+            'ß' => res.push_str("ss"),
+            'À' | 'Á' | 'Â' | 'Ã' | 'Ä' | 'Å' | 'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => res.push('a'),
+            'Ç' | 'ç' => res.push('c'),
+            'È' | 'É' | 'Ê' | 'Ë' | 'è' | 'é' | 'ê' | 'ë' => res.push('e'),
+            'Ì' | 'Í' | 'Î' | 'Ï' | 'ì' | 'í' | 'î' | 'ï' => res.push('i'),
+            'Ñ' | 'ñ' => res.push('n'),
+            'Ò' | 'Ó' | 'Ô' | 'Õ' | 'Ö' | 'Ø' | 'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' => res.push('o'),
+            'Ù' | 'Ú' | 'Û' | 'Ü' | 'ù' | 'ú' | 'û' | 'ü' => res.push('u'),
+            'Ý' | 'ý' | 'ÿ' => res.push('y'),
+            c if c.is_alphanumeric() => res.extend(c.to_lowercase()),
+            c if c.is_whitespace() || c == '-' => {
+                if !res.is_empty() && !res.ends_with(' ') {
+                    res.push(' ')
+                }
+            }
+            _ => (), // commas, apostrophes, ...
+        }
+    }
+    let l = res.trim_end().len();
+    res.truncate(l);
+    res
+}
+
+/// Split a name into its faces: "Fire // Ice" and "Fire/Ice" both give
+/// `["Fire", "Ice"]`. A name without a separator has a single face.
+pub fn faces(name: &str) -> Vec<&str> {
+    name.split('/')
+        .map(|f| f.trim())
+        .filter(|f| !f.is_empty())
+        .collect()
+}
+
+/// The Levenshtein distance between `a` and `b`, counted in characters.
+pub fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr: Vec<usize> = vec![0; b.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        curr[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let subst = prev[j] + if ca == cb { 0 } else { 1 };
+            curr[j + 1] = subst.min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    prev[b.len()]
+}
+
+fn matching<'a, T: Named>(query: &str, db: &'a [T]) -> Vec<&'a T> {
+    db.iter()
+        .filter(|entry| entry.names().iter().any(|n| fold(n) == query))
+        .collect()
+}
+
+/// Return the names in `db` closest to `query`, best first.
+pub fn suggest<T: Named>(query: &str, db: &[T], count: usize) -> Vec<String> {
+    let q = fold(query);
+    let mut scored: Vec<(usize, String)> = db.iter()
+        .map(|entry| {
+            let names = entry.names();
+            let d = names.iter().map(|n| distance(&q, &fold(n))).min().unwrap_or(usize::MAX);
+            (d, names.into_iter().next().unwrap_or_default())
+        })
+        // Anything further away than half the query is noise.
+        .filter(|&(d, _)| d <= (q.chars().count() + 1) / 2)
+        .collect();
+    scored.sort();
+    scored.dedup_by(|a, b| a.1 == b.1);
+    scored.into_iter().take(count).map(|(_, n)| n).collect()
+}
+
+/// Find the entry in `db` named `query`.
+///
+/// An exact match always wins. Otherwise the folded name is matched, first
+/// as a whole and then by its first face, so that "Fire", "fire/ice" and
+/// "Fire // Ice" all find the same split card, and "Delver of Secrets //
+/// Insectile Aberration" finds a card listed by its front face only. A card
+/// we know both faces of isn't found by its front face alone, so "Fire //
+/// Icee" is not found, with "Fire // Ice" suggested.
+pub fn resolve<'a, T: Named>(query: &str, db: &'a [T]) -> Result<&'a T, NotFound> {
+    let query = query.trim();
+
+    if let Some(entry) = db.iter().find(|entry| entry.names().iter().any(|n| n == query)) {
+        return Ok(entry);
+    }
+
+    let mut found = matching(&fold(query), db);
+    let query_faces = faces(query);
+    if found.is_empty() && query_faces.len() > 1 {
+        found = matching(&fold(query_faces[0]), db);
+        found.retain(|entry| entry.names().iter().all(|n| faces(n).len() == 1));
+    }
+
+    // Several printings of the same card are not ambiguous.
+    let mut seen = vec![];
+    found.retain(|entry| {
+        let name = entry.names().into_iter().next();
+        let new = !seen.contains(&name);
+        seen.push(name);
+        new
+    });
+
+    match found.len() {
+        1 => Ok(found[0]),
+        0 => Err(NotFound {
+            query: query.to_string(),
+            ambiguous: false,
+            suggestions: suggest(query, db, NUM_SUGGESTIONS),
+        }),
+        _ => Err(NotFound {
+            query: query.to_string(),
+            ambiguous: true,
+            suggestions: found.iter()
+                .filter_map(|entry| entry.names().into_iter().next())
+                .collect(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct N(Vec<&'static str>);
+
+    impl Named for N {
+        fn names(&self) -> Vec<String> {
+            self.0.iter().map(|s| s.to_string()).collect()
+        }
+    }
+
+    #[test]
+    fn resolve_names() {
+        let db = vec![
+            N(vec!["Æther Hub"]),
+            N(vec!["Urborg, Tomb of Yawgmoth", "Urb"]),
+            N(vec!["Fire // Ice", "Fire", "Ice"]),
+            N(vec!["Polluted Delta", "UBf"]),
+            N(vec!["Darkslick Shores", "UBF"]),
+        ];
+
+        assert_eq!(fold("Æther  Hub"), "aether hub");
+        assert_eq!(distance("kitten", "sitting"), 3);
+
+        assert_eq!(resolve("Aether Hub", &db).unwrap().0[0], "Æther Hub");
+        assert_eq!(resolve("urborg tomb of yawgmoth", &db).unwrap().0[1], "Urb");
+        assert_eq!(resolve("Fire/Ice", &db).unwrap().0[0], "Fire // Ice");
+        assert_eq!(resolve("UBF", &db).unwrap().0[0], "Darkslick Shores");
+        assert!(resolve("ubf", &db).unwrap_err().ambiguous);
+
+        let err = resolve("Poluted Detla", &db).unwrap_err();
+        assert_eq!(err.suggestions, vec!["Polluted Delta".to_string()]);
+
+        // The front face finds a card listed by it alone, but not one whose
+        // back face is another.
+        let db = vec![N(vec!["Fire // Ice", "Fire", "Ice"]), N(vec!["Delver of Secrets"]), N(vec!["Ōkina Nightwatch"])];
+        assert_eq!(resolve("Delver of Secrets // Insectile Aberration", &db).unwrap().0[0], "Delver of Secrets");
+        let err = resolve("Fire // Icee", &db).unwrap_err();
+        assert!(!err.ambiguous);
+        assert_eq!(err.suggestions, vec!["Fire // Ice".to_string()]);
+
+        // Beyond Latin-1 only the case is folded.
+        assert_eq!(resolve("ŌKINA NIGHTWATCH", &db).unwrap().0[0], "Ōkina Nightwatch");
+        let err = resolve("Okina Nightwatch", &db).unwrap_err();
+        assert_eq!(err.suggestions, vec!["Ōkina Nightwatch".to_string()]);
+    }
+}
@@ -0,0 +1,85 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+//
+// Spreading independent computations, like the cells of a table, over the
+// CPU cores. Each thread takes the next item that nobody has started on, so
+// slow items don't hold up the rest, and the results come back in order.
+//
+
+// The number of threads to use, 0 for one per core.
+static THREADS: AtomicUsize = AtomicUsize::new(0);
+
+/// Use at most `n` threads, or one per core if `n` is 0.
+pub fn set_threads(n: usize) {
+    THREADS.store(n, Ordering::Relaxed)
+}
+
+pub fn threads() -> usize {
+    match THREADS.load(Ordering::Relaxed) {
+        0 => thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+        n => n,
+    }
+}
+
+/// `items.iter().map(f).collect()`, with `f` running on all the threads.
+pub fn map<T, R, F>(items: &[T], f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    map_on(threads(), items, f)
+}
+
+// `map` on at most `n` threads.
+fn map_on<T, R, F>(n: usize, items: &[T], f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let n = n.min(items.len());
+    if n <= 1 {
+        return items.iter().map(f).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let mut res: Vec<Option<R>> = (0..items.len()).map(|_| None).collect();
+
+    thread::scope(|s| {
+        let workers: Vec<_> = (0..n)
+            .map(|_| {
+                s.spawn(|| {
+                    let mut done = vec![];
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        if i >= items.len() {
+                            return done;
+                        }
+                        done.push((i, f(&items[i])))
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            for (i, r) in worker.join().unwrap() {
+                res[i] = Some(r)
+            }
+        }
+    });
+
+    res.into_iter().map(|r| r.unwrap()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn in_order() {
+        let items: Vec<u64> = (0..100).collect();
+        let squares = map_on(4, &items, |&i| i * i);
+        assert_eq!(squares, items.iter().map(|&i| i * i).collect::<Vec<_>>());
+    }
+}
@@ -1,5 +1,8 @@
+use crate::colors::Color;
+use crate::keys::Keys;
+use crate::mana::Mana;
 use crate::perm::MultiSubSetIterator;
-use crate::prob;
+use crate::prob::Number;
 use std::fmt;
 use std::ops::{Add, Index, Sub};
 
@@ -13,7 +16,12 @@ pub trait Pile: Add + Sub + Sized {
     // Calculate the probability of drawing exactly `draw` given a `self` as a deck.
     fn prob_draw(self: &Self, draw: &Self) -> f64
     {
-        prob::hyp(self.num_keys(), |idx: usize| {
+        self.prob_draw_as(draw)
+    }
+
+    fn prob_draw_as<N: Number>(self: &Self, draw: &Self) -> N
+    {
+        N::hyp(self.num_keys(), |idx: usize| {
             (self.get(idx), draw.get(idx))
         })
     }
@@ -27,58 +35,122 @@ pub trait Pile: Add + Sub + Sized {
     fn has(&self, other: &Self) -> bool {
         (0..self.num_keys()).all(|k| self.get(k) >= other.get(k))
     }
+
+    /// Iterate over all the piles of `n` cards that can be drawn from `self`.
+    fn subsets<'a>(&'a self, n: usize) -> Box<dyn Iterator<Item = Self> + 'a>;
 }
 
 pub trait LandPile {
     fn spells(&self) -> usize;
     fn lands(&self) -> usize;
 
+    /// Pick `lands` lands and `spells` spells from `self` to put on the bottom
+    /// after a mulligan, least useful first.
+    fn bottom(&self, lands: usize, spells: usize) -> Self where Self: Sized;
+
+    /// Pick the land from `self` to play, given the lands already `played`,
+    /// or `None` if there is none.
+    fn land_drop(&self, played: &Self) -> Option<Self> where Self: Sized;
+
     fn prob_land(&self, l: usize, s: usize) -> f64 {
+        self.prob_land_as(l, s)
+    }
+
+    fn prob_land_as<N: Number>(&self, l: usize, s: usize) -> N {
         let ls = self.lands();
         let ss = self.spells();
-        prob::h(ls as u64, l as u64, ss as u64, s as u64)
+        N::h(ls as u64, l as u64, ss as u64, s as u64)
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct GenPileKeys {
     num_keys: usize,
-    is_land: fn(usize) -> bool,
+    lands: u64, // bit `i` is set when key `i` is a land
+    colors: [u8; 64],
+    tapped: u64,
 }
 
 impl GenPileKeys {
     pub fn new(keys: usize, lands: fn(usize) -> bool) -> GenPileKeys {
+        GenPileKeys::with_lands(keys, (0..keys).filter(|&i| lands(i)).fold(0, |m, i| m | 1 << i))
+    }
+
+    /// Keys where bit `i` of `lands` tells if key `i` is a land.
+    pub fn with_lands(keys: usize, lands: u64) -> GenPileKeys {
+        assert!(keys <= 64, "At most 64 keys");
         GenPileKeys {
             num_keys: keys,
-            is_land: lands,
+            lands,
+            colors: [0; 64],
+            tapped: 0,
         }
     }
+
+    /// The same keys, with the land at `key` tapping for the colors of
+    /// `mask`, and entering tapped if `tapped` is set.
+    pub fn with_land(mut self, key: usize, mask: u8, tapped: bool) -> GenPileKeys {
+        self.colors[key] = mask;
+        if tapped {
+            self.tapped |= 1 << key
+        }
+        self
+    }
 }
 
-impl PartialEq for GenPileKeys {
-    fn eq(&self, b: &GenPileKeys) -> bool {
-        self.num_keys == b.num_keys && (self.is_land as *const u8) == (b.is_land as *const u8)
+// Untyped keys: a key is its position.
+impl Keys for GenPileKeys {
+    type Key = usize;
+
+    fn size(&self) -> usize {
+        self.num_keys
+    }
+
+    fn to_uint(&self, k: usize) -> usize {
+        k
+    }
+
+    fn from_uint(&self, n: usize) -> usize {
+        n
+    }
+
+    fn is_land(&self, n: usize) -> bool {
+        self.lands & (1 << n) != 0
+    }
+
+    fn land_colors(&self, n: usize) -> u8 {
+        self.colors[n]
+    }
+
+    fn enters_tapped(&self, n: usize) -> bool {
+        self.tapped & (1 << n) != 0
     }
 }
 
-#[derive(Clone)]
-pub struct GenPile {
+/// A pile counting cards by key, for any kind of keys `K`: `GenPileKeys`
+/// when the keys are just positions, or the typed keys of `keys`.
+#[derive(Clone, PartialEq)]
+pub struct GenPile<K = GenPileKeys> {
     e: Vec<usize>,
-    k: GenPileKeys,
+    k: K,
 }
 
-impl Pile for GenPile {
+impl<K: Keys> Pile for GenPile<K> {
     fn num_keys(&self) -> usize {
-        self.k.num_keys
+        self.k.size()
     }
 
     fn get(&self, k: usize) -> usize {
         self.e[k as usize]
     }
+
+    fn subsets<'a>(&'a self, n: usize) -> Box<dyn Iterator<Item = GenPile<K>> + 'a> {
+        GenPile::subsets(self, n)
+    }
 }
 
-impl Add for GenPile {
-    type Output = GenPile;
+impl<K: Keys> Add for GenPile<K> {
+    type Output = GenPile<K>;
 
     fn add(self, other: Self) -> Self {
         assert!(self.k == other.k);
@@ -93,8 +165,8 @@ impl Add for GenPile {
     }
 }
 
-impl Sub for GenPile {
-    type Output = GenPile;
+impl<K: Keys> Sub for GenPile<K> {
+    type Output = GenPile<K>;
 
     fn sub(self, other: Self) -> Self {
         assert!(self.k == other.k);
@@ -110,18 +182,37 @@ impl Sub for GenPile {
     }
 }
 
-impl GenPile {
-    pub fn new(l: Vec<usize>, ks: GenPileKeys) -> GenPile {
+impl<K: Keys> GenPile<K> {
+    pub fn new(l: Vec<usize>, ks: K) -> GenPile<K> {
         GenPile { e: l, k: ks }
     }
 
-    pub fn foreach_possible(&self, n: usize, ks: GenPileKeys) -> GenPile {
+    /// The pile with these many cards of each key, and none of the others.
+    pub fn of(ks: K, counts: &[(K::Key, usize)]) -> GenPile<K> {
+        let mut e = vec![0; ks.size()];
+        for &(key, n) in counts {
+            e[ks.to_uint(key)] += n
+        }
+        GenPile { e, k: ks }
+    }
+
+    /// The number of cards with `key`.
+    pub fn cards(&self, key: K::Key) -> usize {
+        self.e[self.k.to_uint(key)]
+    }
+
+    /// Each key, with its number of cards.
+    pub fn counts<'a>(&'a self) -> impl Iterator<Item = (K::Key, usize)> + 'a {
+        (0..self.e.len()).map(move |n| (self.k.from_uint(n), self.e[n]))
+    }
+
+    pub fn foreach_possible(&self, n: usize, ks: K) -> GenPile<K> {
         let sz = self.num_keys();
         let l = (0..sz).map(|idx| if idx == 0 { n } else { 0 }).collect();
         GenPile { e: l, k: ks }
     }
 
-    pub fn subsets<'a>(&'a self, n: usize) -> Box<dyn Iterator<Item = GenPile> + 'a> {
+    pub fn subsets<'a>(&'a self, n: usize) -> Box<dyn Iterator<Item = GenPile<K>> + 'a> {
         Box::new(MultiSubSetIterator::new(&*self.e, n).map(move |e| GenPile { e: e, k: self.k }))
     }
 
@@ -130,7 +221,7 @@ impl GenPile {
     }
 }
 
-impl Index<usize> for GenPile {
+impl<K> Index<usize> for GenPile<K> {
     type Output = usize;
 
     #[inline]
@@ -139,7 +230,7 @@ impl Index<usize> for GenPile {
     }
 }
 
-impl fmt::Debug for GenPile {
+impl<K> fmt::Debug for GenPile<K> {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         write!(fmt, "(")?;
         for (i, v) in self.e.iter().enumerate() {
@@ -152,10 +243,10 @@ impl fmt::Debug for GenPile {
     }
 }
 
-impl Iterator for GenPile {
-    type Item = GenPile;
+impl<K: Keys> Iterator for GenPile<K> {
+    type Item = GenPile<K>;
 
-    fn next(&mut self) -> Option<GenPile> {
+    fn next(&mut self) -> Option<GenPile<K>> {
         let res = GenPile {
             e: self.e.clone(),
             k: self.k,
@@ -185,18 +276,48 @@ impl Iterator for GenPile {
     }
 }
 
-impl LandPile for GenPile {
+impl<K: Keys> LandPile for GenPile<K> {
     fn lands(&self) -> usize {
         self.e
             .iter()
             .enumerate()
-            .map(|(i, v)| if (self.k.is_land)(i) { *v } else { 0 })
+            .map(|(i, v)| if self.k.is_land(i) { *v } else { 0 })
             .sum()
     }
 
     fn spells(&self) -> usize {
         self.total() - self.lands()
     }
+
+    // Without knowing more about the keys, bottom the last keys first. The
+    // exact models bottom what does best for their goal instead.
+    fn bottom(&self, lands: usize, spells: usize) -> GenPile<K> {
+        let mut left = (lands, spells);
+        let e = self.e.iter().enumerate().rev()
+            .map(|(i, &v)| {
+                let n = if self.k.is_land(i) { &mut left.0 } else { &mut left.1 };
+                let t = if v < *n { v } else { *n };
+                *n -= t;
+                t
+            })
+            .collect::<Vec<usize>>()
+            .into_iter()
+            .rev()
+            .collect();
+        GenPile::new(e, self.k)
+    }
+
+    // An untapped land first, and of those one that adds a color we have no
+    // land for yet, as far as the keys tell; else the first land key.
+    fn land_drop(&self, played: &GenPile<K>) -> Option<GenPile<K>> {
+        let have = (0..self.e.len())
+            .filter(|&i| self.k.is_land(i) && played.e[i] > 0)
+            .fold(0, |m, i| m | self.k.land_colors(i));
+        (0..self.e.len())
+            .filter(|&i| self.k.is_land(i) && self.e[i] > 0)
+            .min_by_key(|&i| (self.k.enters_tapped(i), self.k.land_colors(i) & !have == 0))
+            .map(|i| GenPile::new((0..self.e.len()).map(|j| if i == j { 1 } else { 0 }).collect(), self.k))
+    }
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -237,6 +358,13 @@ impl Pile for ColoredPile {
     fn get(&self, i: usize) -> usize {
         self.e[i]
     }
+
+    fn subsets<'a>(&'a self, n: usize) -> Box<dyn Iterator<Item = ColoredPile> + 'a> {
+        if n == 0 {
+            return Box::new(std::iter::once(ColoredPile::new(0, 0, 0)));
+        }
+        Box::new(ColoredPile::foreach_possible(n).filter(move |p| self.has(p)))
+    }
 }
 
 impl Index<usize> for ColoredPile {
@@ -281,6 +409,23 @@ impl LandPile for ColoredPile {
     fn spells(&self) -> usize {
         self.total() - self.lands()
     }
+
+    // Non-colored lands go first.
+    fn bottom(&self, lands: usize, spells: usize) -> ColoredPile {
+        let n = if lands < self.e[1] { lands } else { self.e[1] };
+        ColoredPile::new(lands - n, n, spells)
+    }
+
+    // Colored lands go first.
+    fn land_drop(&self, _played: &ColoredPile) -> Option<ColoredPile> {
+        if self.e[0] > 0 {
+            Some(ColoredPile::new(1, 0, 0))
+        } else if self.e[1] > 0 {
+            Some(ColoredPile::new(0, 1, 0))
+        } else {
+            None
+        }
+    }
 }
 
 impl Iterator for ColoredPile {
@@ -360,6 +505,13 @@ impl Pile for DualPile {
     fn get(&self, k: usize) -> usize {
         self[k]
     }
+
+    fn subsets<'a>(&'a self, n: usize) -> Box<dyn Iterator<Item = DualPile> + 'a> {
+        if n == 0 {
+            return Box::new(std::iter::once(DualPile::new(0, 0, 0, 0, 0)));
+        }
+        Box::new(DualPile::foreach_possible(n).filter(move |p| self.has(p)))
+    }
 }
 
 impl Index<usize> for DualPile {
@@ -407,10 +559,52 @@ impl Sub for DualPile {
 
 impl LandPile for DualPile {
     fn spells(&self) -> usize {
-        self.total() - self.s
+        self.s
     }
     fn lands(&self) -> usize {
-        self.s
+        self.total() - self.s
+    }
+
+    // Colorless lands go first, then whichever of A and B we have more of,
+    // and the duals last.
+    fn bottom(&self, lands: usize, spells: usize) -> DualPile {
+        let mut res = DualPile::new(0, 0, 0, 0, spells);
+        let mut left = *self;
+        for _ in 0..lands {
+            if left.x > 0 {
+                left.x -= 1;
+                res.x += 1
+            } else if left.a > 0 && left.a >= left.b {
+                left.a -= 1;
+                res.a += 1
+            } else if left.b > 0 {
+                left.b -= 1;
+                res.b += 1
+            } else {
+                left.ab -= 1;
+                res.ab += 1
+            }
+        }
+        res
+    }
+
+    // The color we have fewer sources of in play first, then the duals, then
+    // the other color, and colorless lands last.
+    fn land_drop(&self, played: &DualPile) -> Option<DualPile> {
+        let a = DualPile::new(1, 0, 0, 0, 0);
+        let b = DualPile::new(0, 1, 0, 0, 0);
+        let (first, second) = if played.a <= played.b { ((self.a, a), (self.b, b)) } else { ((self.b, b), (self.a, a)) };
+        if first.0 > 0 {
+            Some(first.1)
+        } else if self.ab > 0 {
+            Some(DualPile::new(0, 0, 1, 0, 0))
+        } else if second.0 > 0 {
+            Some(second.1)
+        } else if self.x > 0 {
+            Some(DualPile::new(0, 0, 0, 1, 0))
+        } else {
+            None
+        }
     }
 }
 
@@ -460,6 +654,288 @@ impl fmt::Debug for DualPile {
     }
 }
 
+//
+// A pile over any three to five colors (or fewer), with a key for each mono,
+// dual and tri color source, then colorless lands and spells. The keys are
+// color masks, with bit `c as usize` for each color `c` the land taps for, so
+// a colorless land is `mask(&[Color::C])` and the spells are `0`.
+//
+// With two colors A and B the keys are A, B, AB, colorless and spells, just
+// like a `DualPile`, and we bottom and play lands the same way.
+//
+
+pub const MAX_SOURCES: usize = 25; // with five colors: 5 mono, 10 dual and 10 tri
+
+/// The color mask of a land tapping for `colors`.
+pub fn mask(colors: &[Color]) -> u8 {
+    colors.iter().fold(0, |m, &c| m | 1 << c as usize)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ManaKeys {
+    colors: [Color; 5],
+    num_colors: usize,
+    masks: [u8; MAX_SOURCES],
+    num_masks: usize,
+}
+
+impl ManaKeys {
+    pub fn new(colors: &[Color]) -> ManaKeys {
+        assert!(colors.len() <= 5, "At most five colors");
+        for (i, c) in colors.iter().enumerate() {
+            assert!(*c != Color::C && !colors[..i].contains(c), "Bad colors {:?}", colors);
+        }
+
+        let mut ks = ManaKeys {
+            colors: [Color::C; 5],
+            num_colors: colors.len(),
+            masks: [0; MAX_SOURCES],
+            num_masks: 0,
+        };
+        ks.colors[..colors.len()].copy_from_slice(colors);
+        let n = colors.len();
+        let mut combos: Vec<Vec<usize>> = (0..n).map(|i| vec![i]).collect();
+        for i in 0..n {
+            for j in i + 1..n {
+                combos.push(vec![i, j])
+            }
+        }
+        for i in 0..n {
+            for j in i + 1..n {
+                for k in j + 1..n {
+                    combos.push(vec![i, j, k])
+                }
+            }
+        }
+        for combo in combos {
+            ks.masks[ks.num_masks] = combo.iter().fold(0, |m, &i| m | 1 << colors[i] as usize);
+            ks.num_masks += 1
+        }
+        ks
+    }
+
+    pub fn colors(&self) -> &[Color] {
+        &self.colors[..self.num_colors]
+    }
+
+    fn colorless(&self) -> usize {
+        self.num_masks
+    }
+
+    fn spells(&self) -> usize {
+        self.num_masks + 1
+    }
+
+    fn mask(&self, n: usize) -> u8 {
+        self.from_uint(n)
+    }
+}
+
+impl Keys for ManaKeys {
+    type Key = u8;
+
+    fn size(&self) -> usize {
+        self.num_masks + 2
+    }
+
+    fn to_uint(&self, m: u8) -> usize {
+        if m == 0 {
+            self.spells()
+        } else if m == mask(&[Color::C]) {
+            self.colorless()
+        } else {
+            self.masks[..self.num_masks]
+                .iter()
+                .position(|&k| k == m)
+                .unwrap_or_else(|| panic!("No lands of mask {:#b} with colors {:?}", m, self.colors()))
+        }
+    }
+
+    fn from_uint(&self, n: usize) -> u8 {
+        if n < self.num_masks {
+            self.masks[n]
+        } else if n == self.colorless() {
+            mask(&[Color::C])
+        } else if n == self.spells() {
+            0
+        } else {
+            panic!("Mana key {} out of range", n)
+        }
+    }
+
+    fn is_land(&self, n: usize) -> bool {
+        n != self.spells()
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct ManaPile {
+    e: Vec<usize>,
+    k: ManaKeys,
+}
+
+impl ManaPile {
+    /// A pile of `spells` spells and, for each `(colors, n)` of `lands`, `n`
+    /// lands tapping for `colors`, which can be `[Color::C]`.
+    pub fn new(colors: &[Color], lands: &[(&[Color], usize)], spells: usize) -> ManaPile {
+        let ks = ManaKeys::new(colors);
+        let mut e = vec![0; ks.size()];
+        for &(cs, n) in lands {
+            e[ks.to_uint(mask(cs))] += n
+        }
+        e[ks.spells()] = spells;
+        ManaPile { e, k: ks }
+    }
+
+    fn empty(ks: ManaKeys) -> ManaPile {
+        ManaPile { e: vec![0; ks.size()], k: ks }
+    }
+
+    pub fn keys(&self) -> ManaKeys {
+        self.k
+    }
+
+    /// The number of lands tapping for `color`.
+    pub fn sources(&self, color: Color) -> usize {
+        let c = mask(&[color]);
+        (0..self.k.spells()).filter(|&i| self.k.mask(i) & c != 0).map(|i| self.e[i]).sum()
+    }
+
+    /// Whether the lands can pay for `cost`, each land paying one mana of a
+    /// color it taps for. There is a way to pay when, for every set of colors
+    /// in the cost, there are as many lands tapping for any of them as there
+    /// are pips of them (Hall's theorem), and as many lands as the cost's
+    /// mana value.
+    pub fn can_pay(&self, cost: &Mana) -> bool {
+        if self.lands() < cost.cmc() as usize {
+            return false;
+        }
+        let pips = [cost.w, cost.u, cost.b, cost.r, cost.g, cost.c];
+        let need = (0..6).filter(|&c| pips[c] > 0).fold(0u8, |m, c| m | 1 << c);
+
+        // Each non-empty subset of the colors needed.
+        let mut s = need;
+        while s > 0 {
+            let want: u32 = (0..6).filter(|&c| s & 1 << c != 0).map(|c| pips[c]).sum();
+            let have: usize = (0..self.k.spells()).filter(|&i| self.k.mask(i) & s != 0).map(|i| self.e[i]).sum();
+            if have < want as usize {
+                return false;
+            }
+            s = (s - 1) & need
+        }
+        true
+    }
+}
+
+impl Pile for ManaPile {
+    fn num_keys(&self) -> usize {
+        self.k.size()
+    }
+
+    fn get(&self, k: usize) -> usize {
+        self.e[k]
+    }
+
+    fn subsets<'a>(&'a self, n: usize) -> Box<dyn Iterator<Item = ManaPile> + 'a> {
+        Box::new(MultiSubSetIterator::new(&*self.e, n).map(move |e| ManaPile { e, k: self.k }))
+    }
+}
+
+impl Index<usize> for ManaPile {
+    type Output = usize;
+
+    fn index(&self, k: usize) -> &usize {
+        &self.e[k]
+    }
+}
+
+impl Add for ManaPile {
+    type Output = ManaPile;
+
+    fn add(self, other: ManaPile) -> ManaPile {
+        assert!(self.k == other.k);
+        ManaPile { e: self.e.iter().zip(other.e.iter()).map(|(&i0, &i1)| i0 + i1).collect(), k: self.k }
+    }
+}
+
+impl Sub for ManaPile {
+    type Output = ManaPile;
+
+    fn sub(self, other: ManaPile) -> ManaPile {
+        assert!(self.k == other.k);
+        assert!(self.has(&other));
+        ManaPile { e: self.e.iter().zip(other.e.iter()).map(|(&i0, &i1)| i0 - i1).collect(), k: self.k }
+    }
+}
+
+impl LandPile for ManaPile {
+    fn spells(&self) -> usize {
+        self.e[self.k.spells()]
+    }
+
+    fn lands(&self) -> usize {
+        self.total() - self.spells()
+    }
+
+    // Colorless lands go first, then the lands of the fewest colors, of
+    // those the kind we have the most of, and the tri lands last.
+    fn bottom(&self, lands: usize, spells: usize) -> ManaPile {
+        let mut res = ManaPile::empty(self.k);
+        let mut left = self.clone();
+        res.e[self.k.spells()] = spells;
+        for _ in 0..lands {
+            let i = (0..self.k.spells())
+                .filter(|&i| left.e[i] > 0)
+                .min_by_key(|&i| {
+                    let colors = if i == self.k.colorless() { 0 } else { self.k.mask(i).count_ones() };
+                    (colors, std::cmp::Reverse(left.e[i]))
+                })
+                .expect("Not enough lands to bottom");
+            left.e[i] -= 1;
+            res.e[i] += 1
+        }
+        res
+    }
+
+    // A land for the color we have the fewest sources of in play, one of the
+    // fewest colors first, then for the next color, and colorless lands last.
+    fn land_drop(&self, played: &ManaPile) -> Option<ManaPile> {
+        let mut colors = self.k.colors().to_vec();
+        colors.sort_by_key(|&c| played.sources(c));
+        colors
+            .iter()
+            .filter_map(|&c| {
+                (0..self.k.colorless())
+                    .filter(|&i| self.e[i] > 0 && self.k.mask(i) & mask(&[c]) != 0)
+                    .min_by_key(|&i| self.k.mask(i).count_ones())
+            })
+            .next()
+            .or_else(|| Some(self.k.colorless()).filter(|&i| self.e[i] > 0))
+            .map(|i| {
+                let mut drop = ManaPile::empty(self.k);
+                drop.e[i] = 1;
+                drop
+            })
+    }
+}
+
+impl fmt::Debug for ManaPile {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "[")?;
+        for i in 0..self.e.len() {
+            let name = if i == self.k.colorless() {
+                "x".to_string()
+            } else if i == self.k.spells() {
+                "s".to_string()
+            } else {
+                self.k.colors().iter().filter(|&&c| self.k.mask(i) & mask(&[c]) != 0).map(|c| format!("{:?}", c)).collect()
+            };
+            write!(fmt, "{}{}:{:3}", if i > 0 { ", " } else { "" }, name, self.e[i])?
+        }
+        write!(fmt, "]")
+    }
+}
+
 // mset = [0,1,2,2,2,3,3]
 // nums = [0,1,2,2]
 pub fn mc_next(mset: &[usize], nums: &mut [usize]) -> bool {
@@ -1,3 +1,7 @@
+use num::traits::{One, ToPrimitive, Zero};
+use num::{BigInt, BigRational};
+use std::ops::{Add, Div, Mul, Sub};
+
 //
 // First probabilities
 //
@@ -67,10 +71,78 @@ pub fn ch(n: usize, k: usize) -> usize {
     }
 }
 
+//
+// Log-space combinatorics, for large libraries (Commander decks, cube pools).
+//
+// There the binomials get huge, and multiplying them out costs precision
+// and, for big enough piles, overflows. `h` and `hyp` switch to logarithms
+// for piles of more than `LOG_SPACE_N` cards; 40 and 60 card decks keep the
+// direct products, and so the same results as ever.
+//
+
+pub const LOG_SPACE_N: u64 = 80;
+
+// Lanczos approximation, g = 7, n = 9.
+const LANCZOS: [f64; 9] = [
+    0.99999999999980993,
+    676.5203681218851,
+    -1259.1392167224028,
+    771.32342877765313,
+    -176.61502916214059,
+    12.507343278686905,
+    -0.13857109526572012,
+    9.9843695780195716e-6,
+    1.5056327351493116e-7,
+];
+
+/// ln Γ(x), for x > 0.
+pub fn ln_gamma(x: f64) -> f64 {
+    if x < 0.5 {
+        // Reflection, Γ(x) Γ(1 - x) = π / sin(πx).
+        let pi = std::f64::consts::PI;
+        return (pi / (pi * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let a = (1..9).fold(LANCZOS[0], |a, i| a + LANCZOS[i] / (x + i as f64));
+    let t = x + 7.5;
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + a.ln()
+}
+
+/// ln n!
+pub fn ln_fact(n: u64) -> f64 {
+    if n < 2 {
+        0.0
+    } else {
+        ln_gamma(n as f64 + 1.0)
+    }
+}
+
+/// ln c(n, k), -∞ when `k > n`.
+pub fn ln_c(n: u64, k: u64) -> f64 {
+    if k > n {
+        std::f64::NEG_INFINITY
+    } else if k == 0 || k == n {
+        0.0
+    } else {
+        ln_fact(n) - ln_fact(k) - ln_fact(n - k)
+    }
+}
+
+fn ln_hyp(n: usize, f: &impl Fn(usize) -> (usize, usize)) -> f64 {
+    let (n_t, k_t, ln_t) = (0..n).fold((0, 0, 0.0), |(n_t, k_t, ln_t), i| {
+        let (n, k) = f(i);
+        (n_t + n, k_t + k, ln_t + ln_c(n as u64, k as u64))
+    });
+    ln_t - ln_c(n_t as u64, k_t as u64)
+}
+
 //
 // Given n0 red balls and n1 white balls, the chance of drawing k0 red balls and k1 white balls.
 //
 pub fn h(n0: u64, k0: u64, n1: u64, k1: u64) -> f64 {
+    if n0 + n1 > LOG_SPACE_N {
+        return (ln_c(n0, k0) + ln_c(n1, k1) - ln_c(n0 + n1, k0 + k1)).exp();
+    }
     //                      c(n0, k0) * c(n1, k1)
     // h(n0, k0, n1, k2) = -----------------------
     //                      c(n0 + n1, k0 + k1)
@@ -90,6 +162,9 @@ fn h3(num: [u64; 3], den: [u64; 3]) -> f64 {
 }
 
 pub fn hyp(n: usize, f: impl Fn(usize) -> (usize, usize)) -> f64 {
+    if (0..n).map(|i| f(i).0 as u64).sum::<u64>() > LOG_SPACE_N {
+        return ln_hyp(n, &f).exp();
+    }
     let (n_t, k_t, c_t) = (0..n).fold((0, 0, 1.0), |(n_t, k_t, c_t), i| {
         let (n, k) = f(i);
         (n_t + n, k_t + k, c_t * c(n as u64, k as u64))
@@ -97,6 +172,27 @@ pub fn hyp(n: usize, f: impl Fn(usize) -> (usize, usize)) -> f64 {
     c_t / c(n_t as u64, k_t as u64)
 }
 
+/// The chance of drawing exactly `k` of the `successes` cards in `draws`
+/// cards from a pile of `population` cards.
+pub fn hyp_pmf(population: u64, successes: u64, draws: u64, k: u64) -> f64 {
+    if successes > population || draws > population || k > draws {
+        return 0.0;
+    }
+    h(successes, k, population - successes, draws - k)
+}
+
+/// The chance of drawing at most `k` of the `successes` cards in `draws`
+/// cards from a pile of `population` cards.
+pub fn hyp_cdf(population: u64, successes: u64, draws: u64, k: u64) -> f64 {
+    let lo = (draws + successes).saturating_sub(population);
+    let hi = k.min(successes).min(draws);
+    if lo > hi {
+        return 0.0;
+    }
+    let p: f64 = (lo..=hi).map(|i| hyp_pmf(population, successes, draws, i)).sum();
+    p.min(1.0)
+}
+
 pub fn when<F>(cond: bool, what: F) -> f64
 where
     F: Fn() -> f64,
@@ -115,3 +211,117 @@ pub fn cond(c: bool) -> f64 {
         0.0
     }
 }
+
+//
+// Exact probabilities, as big rationals. Slower, but free of rounding, e.g.
+// for checking that a threshold search didn't come out off by one.
+//
+
+pub fn c_exact(n: u64, k: u64) -> BigInt {
+    if k > n {
+        return BigInt::zero();
+    }
+    let k = if k + k > n { n - k } else { k };
+    // Each partial product is itself a binomial, so the division is exact.
+    (0..k).fold(BigInt::one(), |c, j| c * BigInt::from(n - j) / BigInt::from(j + 1))
+}
+
+pub fn h_exact(n0: u64, k0: u64, n1: u64, k1: u64) -> BigRational {
+    BigRational::new(c_exact(n0, k0) * c_exact(n1, k1), c_exact(n0 + n1, k0 + k1))
+}
+
+pub fn hyp_exact(n: usize, f: impl Fn(usize) -> (usize, usize)) -> BigRational {
+    let (n_t, k_t, c_t) = (0..n).fold((0, 0, BigInt::one()), |(n_t, k_t, c_t), i| {
+        let (n, k) = f(i);
+        (n_t + n, k_t + k, c_t * c_exact(n as u64, k as u64))
+    });
+    BigRational::new(c_t, c_exact(n_t as u64, k_t as u64))
+}
+
+/// The numbers probabilities are computed in: `f64`, or `BigRational` to
+/// compute them exactly.
+pub trait Number:
+    Clone + PartialOrd + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> + Div<Output = Self>
+{
+    fn zero() -> Self;
+    fn one() -> Self;
+    fn ratio(num: usize, den: usize) -> Self;
+    fn h(n0: u64, k0: u64, n1: u64, k1: u64) -> Self;
+    fn hyp(n: usize, f: impl Fn(usize) -> (usize, usize)) -> Self;
+    fn to_f64(&self) -> f64;
+
+    fn cond(c: bool) -> Self {
+        if c { Self::one() } else { Self::zero() }
+    }
+}
+
+impl Number for f64 {
+    fn zero() -> f64 {
+        0.0
+    }
+    fn one() -> f64 {
+        1.0
+    }
+    fn ratio(num: usize, den: usize) -> f64 {
+        num as f64 / den as f64
+    }
+    fn h(n0: u64, k0: u64, n1: u64, k1: u64) -> f64 {
+        h(n0, k0, n1, k1)
+    }
+    fn hyp(n: usize, f: impl Fn(usize) -> (usize, usize)) -> f64 {
+        hyp(n, f)
+    }
+    fn to_f64(&self) -> f64 {
+        *self
+    }
+}
+
+impl Number for BigRational {
+    fn zero() -> BigRational {
+        Zero::zero()
+    }
+    fn one() -> BigRational {
+        One::one()
+    }
+    fn ratio(num: usize, den: usize) -> BigRational {
+        BigRational::new(BigInt::from(num), BigInt::from(den))
+    }
+    fn h(n0: u64, k0: u64, n1: u64, k1: u64) -> BigRational {
+        h_exact(n0, k0, n1, k1)
+    }
+    fn hyp(n: usize, f: impl Fn(usize) -> (usize, usize)) -> BigRational {
+        hyp_exact(n, f)
+    }
+    fn to_f64(&self) -> f64 {
+        ToPrimitive::to_f64(self).unwrap_or(std::f64::NAN)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() <= 1e-12 * b.abs().max(1e-300)
+    }
+
+    #[test]
+    fn log_space() {
+        assert!(close(ln_c(60, 7).exp(), c(60, 7)));
+        assert!(close(ln_fact(20).exp(), 2432902008176640000.0));
+
+        // A Commander deck, 38 lands out of 99: 3 lands in the first 7.
+        let exact = Number::to_f64(&h_exact(38, 3, 61, 4));
+        assert!(close(h(38, 3, 61, 4), exact));
+        assert!(close(hyp(2, |i| [(38, 3), (61, 4)][i]), exact));
+        assert!(close(hyp_pmf(99, 38, 7, 3), exact));
+
+        // Way past the direct products: c(2000, 1000) overflows.
+        assert!(c(2000, 1000).is_infinite());
+        let p = h(1000, 500, 1000, 500);
+        assert!(close(p, Number::to_f64(&h_exact(1000, 500, 1000, 500))));
+
+        assert!(close(hyp_cdf(250, 45, 45, 45), 1.0));
+        assert_eq!(hyp_cdf(99, 38, 70, 8), 0.0);
+    }
+}
@@ -0,0 +1,240 @@
+use crate::colors::Color;
+use crate::deck::{Deck, Entry};
+use crate::diag::Diagnostic;
+use crate::land::{self, LandCardInfo};
+use crate::logic::{single, PlayDraw, Rules};
+use crate::names;
+use crate::table::Table;
+use crate::table::TableElem::{LStr, RStr, U32};
+
+//
+// Sideboard plans.
+//
+// A plan file lists, per matchup, the cards to take out of the main deck and
+// the cards to bring in from the sideboard:
+//
+//     vs Control
+//     -2 Opt
+//     +2 Duress
+//
+// or on a single line: `-2 Opt, +2 Duress vs Control`. Comments (`//` or
+// `#`) and empty lines are skipped.
+//
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Swap {
+    pub count: u32,
+    pub name: String,
+    pub bring_in: bool,
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Plan {
+    pub matchup: String,
+    pub swaps: Vec<Swap>,
+    /// The line of the `vs <matchup>`.
+    pub line: usize,
+}
+
+fn parse_swap(line_no: usize, column: usize, item: &str) -> Result<Swap, Diagnostic> {
+    let item = item.trim();
+    let (bring_in, rest) = if item.starts_with('+') {
+        (true, &item[1..])
+    } else if item.starts_with('-') {
+        (false, &item[1..])
+    } else {
+        return Err(Diagnostic::error(line_no, column,
+            format!("Expected '+<count> <card>' or '-<count> <card>', found '{}'", item)));
+    };
+
+    match crate::deck::parse_line(line_no, rest) {
+        Ok(Some(e)) => Ok(Swap { count: e.count, name: e.name, bring_in, line: line_no, column }),
+        Ok(None) => Err(Diagnostic::error(line_no, column, "Missing card after '+' or '-'".to_string())),
+        Err(mut d) => {
+            d.column = column;
+            Err(d)
+        }
+    }
+}
+
+pub fn parse_plans(text: &str) -> (Vec<Plan>, Vec<Diagnostic>) {
+    let mut plans: Vec<Plan> = vec![];
+    let mut diags = vec![];
+
+    for (line_no, raw) in text.split('\n').enumerate() {
+        let line_no = line_no + 1;
+        let line = raw.trim();
+
+        if line.len() == 0 || line.starts_with("//") || line.starts_with('#') {
+            continue;
+        }
+
+        let (swaps, matchup) = if line.starts_with("vs ") {
+            ("", Some(line[3..].trim()))
+        } else {
+            match line.find(" vs ") {
+                Some(i) => (&line[..i], Some(line[i + 4..].trim())),
+                None => (line, None),
+            }
+        };
+
+        if let Some(m) = matchup {
+            plans.push(Plan { matchup: m.to_string(), swaps: vec![], line: line_no });
+        }
+
+        let mut column = 1 + raw.chars().take_while(|c| c.is_whitespace()).count();
+        for item in swaps.split(',').filter(|i| !i.trim().is_empty()) {
+            let col = column + item.chars().take_while(|c| c.is_whitespace()).count();
+            column += item.chars().count() + 1;
+
+            match (parse_swap(line_no, col, item), plans.last_mut()) {
+                (Ok(s), Some(p)) => p.swaps.push(s),
+                (Ok(_), None) => diags.push(Diagnostic::error(line_no, col,
+                    "Swap before the first 'vs <matchup>' line".to_string())),
+                (Err(d), _) => diags.push(d),
+            }
+        }
+    }
+
+    (plans, diags)
+}
+
+fn take(entries: &mut Vec<Entry>, swap: &Swap, from: &str) -> Result<(), Diagnostic> {
+    let name = names::fold(&swap.name);
+    match entries.iter_mut().find(|e| names::fold(&e.name) == name) {
+        Some(e) => {
+            if e.count >= swap.count {
+                e.count -= swap.count;
+                Ok(())
+            } else {
+                Err(Diagnostic::error(swap.line, swap.column,
+                    format!("Can't take {} '{}' from the {}, there are only {} left",
+                            swap.count, swap.name, from, e.count)))
+            }
+        }
+        None => Err(Diagnostic::error(swap.line, swap.column,
+            format!("'{}' is not in the {}", swap.name, from))),
+    }
+}
+
+fn put(entries: &mut Vec<Entry>, swap: &Swap) {
+    let name = names::fold(&swap.name);
+    match entries.iter_mut().find(|e| names::fold(&e.name) == name) {
+        Some(e) => e.count += swap.count,
+        None => entries.push(Entry { count: swap.count, name: swap.name.clone(), line: swap.line, column: swap.column }),
+    }
+}
+
+/// The deck after sideboarding according to `plan`. Swaps that are not
+/// possible are reported as errors, and left out.
+pub fn apply(deck: &Deck, plan: &Plan) -> (Deck, Vec<Diagnostic>) {
+    let mut res = deck.clone();
+    let mut diags = vec![];
+
+    for swap in plan.swaps.iter() {
+        let r = if swap.bring_in {
+            take(&mut res.sideboard, swap, "sideboard").map(|_| put(&mut res.main, swap))
+        } else {
+            take(&mut res.main, swap, "main deck").map(|_| put(&mut res.sideboard, swap))
+        };
+        if let Err(d) = r {
+            diags.push(d)
+        }
+    }
+
+    let (ins, outs) = plan.swaps.iter().fold((0, 0), |(i, o), s| {
+        if s.bring_in { (i + s.count, o) } else { (i, o + s.count) }
+    });
+    if ins != outs {
+        diags.push(Diagnostic::warning(plan.line, 1,
+            format!("Plan vs {} brings in {} cards and takes out {}", plan.matchup, ins, outs)));
+    }
+
+    res.main.retain(|e| e.count > 0);
+    res.sideboard.retain(|e| e.count > 0);
+    (res, diags)
+}
+
+// Lands, deck size and colored sources (W, U, B, R, G) of the main deck. The
+// names `find_lands` warns about are counted as spells.
+fn mana_base(deck: &Deck, db: &Vec<LandCardInfo>) -> (u32, u32, Vec<(Color, u32)>) {
+    let (ls, _) = land::find_lands(&deck.main, db);
+    let (lands, m) = land::sources(&ls);
+    let colors = vec![(Color::W, m.w), (Color::U, m.u), (Color::B, m.b), (Color::R, m.r), (Color::G, m.g)];
+    (lands, deck.main_count(), colors)
+}
+
+/// Compare the colored sources and the chance of casting C, CC and CCC on
+/// curve between game 1 (`before`) and after sideboarding (`after`), `play`ing
+/// with `rules`.
+pub fn castability(before: &Deck, after: &Deck, db: &Vec<LandCardInfo>, play: PlayDraw, rules: Rules) -> Table {
+    let (l0, d0, c0) = mana_base(before, db);
+    let (l1, d1, c1) = mana_base(after, db);
+
+    let colors: Vec<usize> = (0..c0.len()).filter(|&i| c0[i].1 > 0 || c1[i].1 > 0).collect();
+
+    let mut table = Table::new(1 + colors.len(), 6);
+    table.set(0, 0, LStr(format!("{}/{} -> {}/{}", l0, d0, l1, d1)));
+    table.set(0, 1, RStr("G1".to_string()));
+    table.set(0, 2, RStr("G2".to_string()));
+    for pips in 1..=3 {
+        table.set(0, 2 + pips, RStr(std::iter::repeat('C').take(pips).collect()));
+    }
+
+    let cast = |lands: u32, colored: u32, deck: u32, pips: usize| {
+        if colored == 0 || lands == 0 { 0.0 } else {
+            single::castable(lands as usize, colored as usize, deck as usize, pips, pips, play, rules)
+        }
+    };
+
+    for (row, &i) in colors.iter().enumerate() {
+        let (color, s0) = c0[i];
+        let s1 = c1[i].1;
+        table.set(1 + row, 0, LStr(format!("{:?}", color)));
+        table.set(1 + row, 1, U32(s0));
+        table.set(1 + row, 2, U32(s1));
+        for pips in 1..=3 {
+            let p0 = cast(l0, s0, d0, pips);
+            let p1 = cast(l1, s1, d1, pips);
+            table.set(1 + row, 2 + pips, RStr(format!("{:.1}% ({:+.1})", p1 * 100.0, (p1 - p0) * 100.0)));
+        }
+    }
+
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diag::Severity;
+
+    #[test]
+    fn plans() {
+        let db = land::land_db();
+        let (deck, _) = Deck::parse("9 Island\n8 Swamp\n23 Opt\nSideboard\n2 Duress\n1 Underground Sea\n");
+        let text = "vs Control\n-2 Opt\n+2 Duress\n\n-1 Opt, +1 Underground Sea vs Aggro\nvs Mirror\n+1 Duress\n";
+        let (plans, diags) = parse_plans(text);
+        assert!(diags.is_empty());
+        assert_eq!(plans.iter().map(|p| (&*p.matchup, p.line, p.swaps.len())).collect::<Vec<_>>(),
+                   vec![("Control", 1, 2), ("Aggro", 5, 2), ("Mirror", 6, 1)]);
+
+        let (after, diags) = apply(&deck, &plans[1]);
+        assert!(diags.is_empty());
+        assert_eq!((after.main[2].count, after.sideboard[1].count), (22, 1));
+        let (lands, size, colors) = mana_base(&after, &db);
+        assert_eq!((lands, size), (18, 40));
+        assert_eq!((colors[1], colors[2]), ((Color::U, 10), (Color::B, 9)));
+
+        // An unbalanced plan is reported at its `vs` line.
+        let (after, diags) = apply(&deck, &plans[2]);
+        assert_eq!(after.main_count(), 41);
+        assert_eq!(diags.iter().map(|d| (d.line, d.severity)).collect::<Vec<_>>(), vec![(6, Severity::Warning)]);
+
+        // As is a name that could be several lands.
+        let (deck, _) = Deck::parse("17 Island\n1 ubf\n");
+        let (ls, diags) = land::find_lands(&deck.main, &db);
+        assert_eq!((ls.len(), diags.len(), diags[0].line), (1, 1, 2));
+    }
+}
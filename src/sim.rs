@@ -0,0 +1,462 @@
+use crate::category::Categories;
+use crate::colors::Color;
+use crate::logic::battlefield::{Board, Effect, Effects, LandsUntil};
+use crate::logic::{LandWindow, Mulligan, MulliganPolicy, Opening, PlayDraw, Rules, MULLIGANS};
+use crate::pile::{GenPile, LandPile, Pile};
+use crate::table::Table;
+use crate::table::TableElem::{LStr, RStr, U32};
+use std::collections::VecDeque;
+use std::fmt;
+use std::ops::{Add, Sub};
+
+//
+// Monte Carlo simulation.
+//
+// Shuffle a concrete deck, deal and mulligan the opening hand the same way as
+// the exact models in `logic`, draw, and count how often the goal is reached.
+// Everything is driven by our own generator, so a seed always gives the same
+// result.
+//
+
+/// A small, fast generator (xorshift64*, seeded through splitmix64).
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        Rng { state: if z == 0 { 1 } else { z } }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Uniform in `0..n`.
+    pub fn below(&mut self, n: usize) -> usize {
+        let n = n as u64;
+        let zone = u64::max_value() - u64::max_value() % n;
+        loop {
+            let r = self.next_u64();
+            if r < zone {
+                return (r % n) as usize;
+            }
+        }
+    }
+
+    pub fn shuffle<T>(&mut self, v: &mut [T]) {
+        for i in (1..v.len()).rev() {
+            let j = self.below(i + 1);
+            v.swap(i, j)
+        }
+    }
+}
+
+/// The fraction of games reaching the goal, with a 95% confidence interval
+/// (Wilson score).
+#[derive(Copy, Clone, Debug)]
+pub struct Estimate {
+    pub prob: f64,
+    pub low: f64,
+    pub high: f64,
+    pub games: usize,
+}
+
+impl Estimate {
+    pub fn new(successes: usize, games: usize) -> Estimate {
+        let z = 1.96;
+        let n = games as f64;
+        let p = successes as f64 / n;
+        let den = 1.0 + z * z / n;
+        let center = (p + z * z / (2.0 * n)) / den;
+        let half = z * (p * (1.0 - p) / n + z * z / (4.0 * n * n)).sqrt() / den;
+        Estimate { prob: p, low: center - half, high: center + half, games }
+    }
+
+    pub fn contains(&self, p: f64) -> bool {
+        self.low <= p && p <= self.high
+    }
+}
+
+impl fmt::Display for Estimate {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{:.2}% (95% CI {:.2}%–{:.2}%, {} games)",
+               self.prob * 100.0, self.low * 100.0, self.high * 100.0, self.games)
+    }
+}
+
+// A pile spread out into cards, one key per card.
+struct Cards<P> {
+    deck: P,
+    units: Vec<Option<P>>, // a pile of one card, for each key
+    cards: Vec<usize>,
+}
+
+impl<P> Cards<P>
+where
+    P: Pile + LandPile + Clone + Add<Output = P> + Sub<Output = P>,
+{
+    fn new(deck: &P) -> Cards<P> {
+        let mut units = vec![None; deck.num_keys()];
+        for unit in deck.subsets(1) {
+            if let Some(k) = (0..unit.num_keys()).find(|&k| unit.get(k) == 1) {
+                units[k] = Some(unit)
+            }
+        }
+        let cards = (0..deck.num_keys()).flat_map(|k| std::iter::repeat(k).take(deck.get(k))).collect();
+        Cards { deck: deck.clone(), units, cards }
+    }
+
+    fn none(&self) -> P {
+        self.deck.clone() - self.deck.clone()
+    }
+
+    fn unit(&self, card: usize) -> P {
+        self.units[card].clone().unwrap()
+    }
+
+    fn pile(&self, cards: &[usize]) -> P {
+        cards.iter().fold(self.none(), |p, &k| p + self.unit(k))
+    }
+
+    fn lands(&self, cards: &[usize]) -> usize {
+        cards.iter().filter(|&&k| self.unit(k).lands() > 0).count()
+    }
+
+    // The deck in a random order, with a `hand_size` card opening hand first.
+    fn deal(&self, hand_size: usize, opening: Opening, rng: &mut Rng) -> Vec<usize> {
+        let mut order = self.cards.clone();
+        rng.shuffle(&mut order);
+
+        if let Opening::Arena(n) = opening {
+            let target = (hand_size * self.deck.lands()) as f64 / self.deck.total() as f64;
+            let mut best = (self.lands(&order[..hand_size]) as f64 - target).abs();
+            let mut ties = 1;
+            for _ in 1..n {
+                let mut other = self.cards.clone();
+                rng.shuffle(&mut other);
+                let d = (self.lands(&other[..hand_size]) as f64 - target).abs();
+                if d < best - 1e-9 {
+                    best = d;
+                    ties = 1;
+                    order = other
+                } else if d < best + 1e-9 {
+                    // Pick at random between the tied hands.
+                    ties += 1;
+                    if rng.below(ties) == 0 {
+                        order = other
+                    }
+                }
+            }
+        }
+
+        order
+    }
+
+    // Deal until we keep, then see if we reach the goal from the kept hand and
+    // the rest of the deck in order. The bottomed cards are never drawn.
+    fn game(&self, rules: Rules, policy: &dyn MulliganPolicy<P>, rng: &mut Rng, reach: &dyn Fn(P, &[usize]) -> bool) -> bool {
+        for mulls in 0..MULLIGANS {
+            let hand_size = match rules.mulligan {
+                Mulligan::London => 7,
+                Mulligan::Classic => 7 - mulls,
+            };
+            let order = self.deal(hand_size, rules.opening_after(mulls), rng);
+            if let Some(kept) = policy.keep(&self.pile(&order[..hand_size]), mulls) {
+                return reach(kept, &order[hand_size..]);
+            }
+        }
+        false
+    }
+}
+
+fn run<P>(deck: &P, rules: Rules, policy: &dyn MulliganPolicy<P>, games: usize, seed: u64,
+          reach: &dyn Fn(&Cards<P>, P, &[usize]) -> bool) -> Estimate
+where
+    P: Pile + LandPile + Clone + Add<Output = P> + Sub<Output = P>,
+{
+    let cards = Cards::new(deck);
+    let mut rng = Rng::new(seed);
+    let successes = (0..games)
+        .filter(|_| cards.game(rules, policy, &mut rng, &|kept, library| reach(&cards, kept, library)))
+        .count();
+    Estimate::new(successes, games)
+}
+
+/// Estimate the chance of `goal` holding for all the cards seen by turn
+/// `turn`, as `logic::chance` computes exactly.
+pub fn simulate<P, G>(
+    deck: &P,
+    turn: usize,
+    play: PlayDraw,
+    rules: Rules,
+    policy: &dyn MulliganPolicy<P>,
+    goal: G,
+    games: usize,
+    seed: u64,
+) -> Estimate
+where
+    P: Pile + LandPile + Clone + Add<Output = P> + Sub<Output = P>,
+    G: Fn(P) -> bool,
+{
+    let draws = play.draws(turn);
+    run(deck, rules, policy, games, seed, &|cards, kept, library| {
+        goal(kept + cards.pile(&library[..draws.min(library.len())]))
+    })
+}
+
+/// Estimate the chance of `goal` holding for the board at the end of turn
+/// `turn`, playing a land a turn, as `logic::battlefield` computes exactly.
+pub fn simulate_battlefield<P, G>(
+    deck: &P,
+    turn: usize,
+    play: PlayDraw,
+    rules: Rules,
+    policy: &dyn MulliganPolicy<P>,
+    goal: G,
+    games: usize,
+    seed: u64,
+) -> Estimate
+where
+    P: Pile + LandPile + Clone + Add<Output = P> + Sub<Output = P>,
+    G: Fn(&Board<P>) -> bool,
+{
+    run(deck, rules, policy, games, seed, &|cards, kept, library| {
+        let mut board = Board { lands: cards.none(), hand: kept };
+        let mut next = library.iter();
+        for t in 1..=turn {
+            if t > 1 || play == PlayDraw::OnTheDraw {
+                if let Some(&card) = next.next() {
+                    board.hand = board.hand.clone() + cards.unit(card)
+                }
+            }
+            if let Some(land) = board.hand.land_drop(&board.lands) {
+                board.hand = board.hand.clone() - land.clone();
+                board.lands = board.lands.clone() + land;
+            }
+        }
+        goal(&board)
+    })
+}
+
+// A game with effects: where we are, and the library in order.
+struct Play<'a, P> {
+    cards: &'a Cards<P>,
+    fx: &'a Effects<P>,
+    board: Board<P>,
+    library: VecDeque<usize>,
+    mana: usize,
+    dropped: bool,
+}
+
+impl<'a, P> Play<'a, P>
+where
+    P: Pile + LandPile + Clone + Add<Output = P> + Sub<Output = P>,
+{
+    fn apply(&mut self, effects: &[Effect]) {
+        for &effect in effects {
+            match effect {
+                Effect::Draw(n) => {
+                    for _ in 0..n {
+                        if let Some(card) = self.library.pop_front() {
+                            self.board.hand = self.board.hand.clone() + self.cards.unit(card)
+                        }
+                    }
+                }
+                Effect::Scry(n) | Effect::Surveil(n) => {
+                    let seen: Vec<usize> = (0..n).filter_map(|_| self.library.pop_front()).collect();
+                    let (kept, gone): (Vec<usize>, Vec<usize>) =
+                        seen.into_iter().partition(|&card| self.fx.look.keep(&self.board, &self.cards.unit(card)));
+                    for &card in kept.iter().rev() {
+                        self.library.push_front(card)
+                    }
+                    if let Effect::Scry(_) = effect {
+                        self.library.extend(gone)
+                    }
+                }
+            }
+        }
+    }
+
+    fn land_drop(&mut self) {
+        if self.dropped {
+            return;
+        }
+        if let Some(land) = self.board.hand.land_drop(&self.board.lands) {
+            self.board.hand = self.board.hand.clone() - land.clone();
+            self.board.lands = self.board.lands.clone() + land.clone();
+            self.dropped = true;
+            let fx = self.fx;
+            match fx.lands.iter().find(|l| l.card.has(&land) && land.has(&l.card)) {
+                Some(l) => {
+                    if !l.tapped {
+                        self.mana += 1
+                    }
+                    self.apply(&l.effects)
+                }
+                None => self.mana += 1,
+            }
+        }
+    }
+
+    fn turn(&mut self, draw: bool) {
+        if draw {
+            self.apply(&[Effect::Draw(1)])
+        }
+        self.mana = self.board.lands.lands();
+        self.dropped = false;
+        self.land_drop();
+
+        let fx = self.fx;
+        while let Some(c) = fx.cantrips.iter().find(|c| {
+            self.board.hand.has(&c.card) && c.cost <= self.mana && (c.colors)(&self.board.lands)
+        }) {
+            self.board.hand = self.board.hand.clone() - c.card.clone();
+            self.mana -= c.cost;
+            self.apply(&c.effects);
+            self.land_drop()
+        }
+    }
+}
+
+/// `simulate_battlefield`, with the cantrips, scrying and surveilling of
+/// `effects`, as `logic::battlefield::with_effects` computes exactly.
+pub fn simulate_effects<P, G>(
+    deck: &P,
+    turn: usize,
+    play: PlayDraw,
+    rules: Rules,
+    policy: &dyn MulliganPolicy<P>,
+    effects: &Effects<P>,
+    goal: G,
+    games: usize,
+    seed: u64,
+) -> Estimate
+where
+    P: Pile + LandPile + Clone + Add<Output = P> + Sub<Output = P>,
+    G: Fn(&Board<P>) -> bool,
+{
+    run(deck, rules, policy, games, seed, &|cards, kept, library| {
+        let mut game = Play {
+            cards,
+            fx: effects,
+            board: Board { lands: cards.none(), hand: kept },
+            library: library.iter().cloned().collect(),
+            mana: 0,
+            dropped: false,
+        };
+        for t in 1..=turn {
+            game.turn(t > 1 || play == PlayDraw::OnTheDraw)
+        }
+        goal(&game.board)
+    })
+}
+
+/// How scry lands and Opts change the chance of making each land drop and of
+/// having both colors in play, turn by turn, for a two color deck of `lands`
+/// lands, `scry_lands` of them scry lands, with `cantrips` Opts. We keep
+/// lands on top until we have one more than the turn's land drops in hand
+/// and in play.
+pub fn scry_table(lands: usize, deck: usize, scry_lands: usize, cantrips: usize, play: PlayDraw, rules: Rules, games: usize) -> Table {
+    let basics = lands - scry_lands;
+    let mut cats = Categories::new();
+    let island = cats.land("Island", &[Color::U]);
+    let swamp = cats.land("Swamp", &[Color::B]);
+    let temple = cats.land_with("Temple", &[Color::U, Color::B], true, &[Effect::Scry(1)]);
+    let opt = cats.spell_with("Opt", 1, &[Color::U], &[Effect::Scry(1), Effect::Draw(1)]);
+    let spell = cats.spell("Spell", 2, &[]);
+    let pile = cats.pile(&[(island, (basics + 1) / 2), (swamp, basics / 2), (temple, scry_lands), (opt, cantrips),
+                           (spell, deck - lands - cantrips)]);
+
+    let mut t = Table::new(8, 5);
+    t.set(0, 0, LStr(format!("{}/{}, {} scry, {} Opt", lands, deck, scry_lands, cantrips)));
+    for (col, name) in ["drops", "w/ scry", "UB", "w/ scry"].iter().enumerate() {
+        t.set(0, col + 1, RStr(name.to_string()))
+    }
+
+    for turn in 1..=7 {
+        let effects = cats.effects(Box::new(LandsUntil(turn + 1)));
+        let drops = |b: &Board<GenPile>| b.lands.lands() >= turn;
+        let both = |b: &Board<GenPile>| cats.sources(&b.lands, Color::U) > 0 && cats.sources(&b.lands, Color::B) > 0;
+        let res = [
+            simulate_battlefield(&pile, turn, play, rules, &LandWindow, drops, games, 1),
+            simulate_effects(&pile, turn, play, rules, &LandWindow, &effects, drops, games, 1),
+            simulate_battlefield(&pile, turn, play, rules, &LandWindow, both, games, 1),
+            simulate_effects(&pile, turn, play, rules, &LandWindow, &effects, both, games, 1),
+        ];
+        t.set(turn, 0, U32(turn as u32));
+        for (col, est) in res.iter().enumerate() {
+            t.set(turn, col + 1, RStr(format!("{:.1}%", est.prob * 100.0)))
+        }
+    }
+
+    t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic::battlefield::LandsUntil;
+    use crate::logic::{self, battlefield, LandWindow};
+    use crate::category::Categories;
+    use crate::colors::Color;
+    use crate::pile::{ColoredPile, GenPile};
+
+    #[test]
+    fn cross_check() {
+        let deck = ColoredPile::new(10, 7, 23);
+        let play = PlayDraw::OnThePlay;
+        let goal = |hand: ColoredPile| hand.colored() >= 2 && hand.lands() >= 3;
+
+        for &rules in &[Rules::standard(), Rules::arena()] {
+            let exact = logic::chance(&deck, 3, play, rules, &LandWindow, goal);
+            let est = simulate(&deck, 3, play, rules, &LandWindow, goal, 20000, 7);
+            assert!(est.contains(exact), "{} vs {}", est, exact);
+        }
+
+        // After a mulligan to 6 Arena deals as a plain shuffle.
+        let mull_to_6 = |hand: &ColoredPile, mulls: usize| if mulls == 1 { LandWindow.keep(hand, 1) } else { None };
+        let exact = logic::chance(&deck, 3, play, Rules::standard(), &mull_to_6, goal);
+        let est = simulate(&deck, 3, play, Rules::arena(), &mull_to_6, goal, 20000, 7);
+        assert!(est.contains(exact), "{} vs {}", est, exact);
+
+        let goal = |b: &Board<ColoredPile>| b.lands.lands() == 3 && b.hand.lands() > 0;
+        let exact = battlefield::turn0(&deck, 3, play, goal);
+        let est = simulate_battlefield(&deck, 3, play, Rules::standard(), &LandWindow, goal, 20000, 7);
+        assert!(est.contains(exact), "{} vs {}", est, exact);
+
+        // Same seed, same games.
+        let again = simulate_battlefield(&deck, 3, play, Rules::standard(), &LandWindow, goal, 20000, 7);
+        assert_eq!(est.prob, again.prob);
+    }
+
+    #[test]
+    fn effects() {
+        let mut cats = Categories::new();
+        let temple = cats.land_with("Temple", &[Color::U, Color::B], true, &[Effect::Scry(1)]);
+        let opt = cats.spell_with("Opt", 1, &[Color::U], &[Effect::Scry(1), Effect::Draw(1)]);
+        let bear = cats.spell("Bear", 2, &[Color::G]);
+        let deck = cats.pile(&[(temple, 16), (opt, 8), (bear, 16)]);
+        let play = PlayDraw::OnThePlay;
+        let goal = |b: &Board<GenPile>| b.lands.lands() >= 3;
+
+        let without = battlefield::turn0(&deck, 3, play, goal);
+        let effects = cats.effects(Box::new(LandsUntil(4)));
+        let with = battlefield::with_effects(&deck, 3, play, Rules::standard(), &LandWindow, &effects, goal);
+        assert!(with > without + 0.01, "{} vs {}", with, without);
+
+        let est = simulate_effects(&deck, 3, play, Rules::standard(), &LandWindow, &effects, goal, 20000, 7);
+        assert!(est.contains(with), "{} vs {}", est, with);
+
+        // Digging for spells instead finds fewer lands.
+        let spells = cats.effects(Box::new(LandsUntil(0)));
+        assert!(battlefield::with_effects(&deck, 3, play, Rules::standard(), &LandWindow, &spells, goal) < without);
+    }
+}
@@ -0,0 +1,151 @@
+use crate::prob::Number;
+use std::collections::BTreeMap;
+
+//
+// The inverse questions: the fewest cards of some category for the chance of
+// something to reach a threshold, e.g. the fewest colored lands in a 17 land
+// deck to cast a `2C` spell on turn 2 at least 90% as often as with all
+// lands colored:
+//
+//     let solver = Solver { threshold: 0.90, baseline: Baseline::At(17), search: Search::Bisect };
+//     let sol = solver.solve(0, 17, |k| single::turn0(ColoredPile::new(k, 17 - k, 23), 2, play, goal));
+//
+// The solver knows nothing about piles: `chance(k)` builds the deck with `k`
+// cards of the category and works out the chance with whichever model fits.
+//
+
+/// What the threshold is a fraction of.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Baseline<N> {
+    /// Nothing, the threshold is a chance.
+    One,
+    /// This chance.
+    Chance(N),
+    /// The chance with this count.
+    At(usize),
+    /// The best chance of all counts. When bisecting, the chance is taken to
+    /// grow with the count, so this is the chance at the highest count.
+    Best,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Search {
+    /// Try each count from the lowest up, until one is good enough.
+    Scan,
+    /// Work out the chance of every count, and pick the lowest good enough.
+    Full,
+    /// Bisect, for chances that grow with the count.
+    Bisect,
+}
+
+#[derive(Clone, Debug)]
+pub struct Solver<N> {
+    pub threshold: N,
+    pub baseline: Baseline<N>,
+    pub search: Search,
+}
+
+#[derive(Clone, Debug)]
+pub struct Solution<N> {
+    /// The lowest count with a chance of at least the threshold times the
+    /// baseline, if any.
+    pub count: Option<usize>,
+    pub baseline: N,
+    /// The chances we worked out, by count. All of them with `Search::Full`.
+    pub curve: Vec<(usize, N)>,
+}
+
+impl<N: Number> Solution<N> {
+    pub fn chance(&self) -> Option<N> {
+        let count = self.count?;
+        self.curve.iter().find(|&&(k, _)| k == count).map(|(_, p)| p.clone())
+    }
+
+    /// The chance at the count over the baseline, or 0 if the baseline is 0.
+    pub fn ratio(&self) -> Option<N> {
+        let p = self.chance()?;
+        Some(if self.baseline > N::zero() { p / self.baseline.clone() } else { N::zero() })
+    }
+}
+
+impl<N: Number> Solver<N> {
+    /// The lowest count in `lo..=hi` for which `chance` reaches the threshold.
+    pub fn solve<F>(&self, lo: usize, hi: usize, chance: F) -> Solution<N>
+    where
+        F: Fn(usize) -> N,
+    {
+        assert!(lo <= hi);
+        let mut curve = BTreeMap::new();
+        let mut at = |k: usize| curve.entry(k).or_insert_with(|| chance(k)).clone();
+
+        if self.search == Search::Full || (self.search == Search::Scan && matches!(self.baseline, Baseline::Best)) {
+            for k in lo..=hi {
+                at(k);
+            }
+        }
+        let baseline = match self.baseline {
+            Baseline::One => N::one(),
+            Baseline::Chance(ref p) => p.clone(),
+            Baseline::At(k) => at(k),
+            Baseline::Best if self.search == Search::Bisect => at(hi),
+            Baseline::Best => (lo..=hi).map(&mut at).fold(N::zero(), |m, p| if p > m { p } else { m }),
+        };
+        let threshold = self.threshold.clone() * baseline.clone();
+
+        let count = match self.search {
+            Search::Scan | Search::Full => (lo..=hi).find(|&k| at(k) >= threshold),
+            Search::Bisect => {
+                if at(hi) < threshold {
+                    None
+                } else {
+                    // The answer is in `a..=b`.
+                    let (mut a, mut b) = (lo, hi);
+                    while a < b {
+                        let m = a + (b - a) / 2;
+                        if at(m) >= threshold {
+                            b = m
+                        } else {
+                            a = m + 1
+                        }
+                    }
+                    Some(a)
+                }
+            }
+        };
+
+        Solution { count, baseline, curve: curve.into_iter().collect() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic::{single, PlayDraw};
+    use crate::pile::{ColoredPile, LandPile};
+
+    #[test]
+    fn scan_and_bisect() {
+        let goal = |hand: ColoredPile| hand.colored() >= 2 && hand.lands() >= 3;
+        let chance = |k: usize| single::turn0(ColoredPile::new(k, 17 - k, 23), 3, PlayDraw::OnThePlay, goal);
+
+        let mut solver = Solver { threshold: 0.90, baseline: Baseline::At(17), search: Search::Full };
+        let full = solver.solve(0, 17, chance);
+        assert_eq!(full.curve.len(), 18);
+        let k = full.count.unwrap();
+        assert!(full.curve[k].1 >= 0.90 * full.baseline && full.curve[k - 1].1 < 0.90 * full.baseline);
+
+        for &search in &[Search::Scan, Search::Bisect] {
+            solver.search = search;
+            let sol = solver.solve(0, 17, chance);
+            assert_eq!((sol.count, sol.baseline), (full.count, full.baseline));
+            assert_eq!(sol.ratio(), full.ratio());
+            assert!(sol.curve.len() < full.curve.len());
+        }
+
+        // As a chance, rather than a fraction of one.
+        solver.baseline = Baseline::One;
+        assert_eq!(solver.solve(0, 17, chance).count, full.curve.iter().position(|&(_, p)| p >= 0.90));
+        solver.threshold = 1.0;
+        assert_eq!(solver.solve(0, 17, chance).count, None);
+    }
+}
@@ -0,0 +1,225 @@
+use crate::colors::Color;
+use crate::mana::Mana;
+use crate::mtgjson::Card;
+use crate::table::TableElem::{LStr, RStr, U32};
+use crate::table::{left, right, Table};
+use std::iter::repeat;
+
+//
+// Deck statistics: mana curve, card types, color pips and average mana value.
+//
+
+// Mana values 0 to 6, and 7 or more.
+const CURVE_SIZE: usize = 8;
+
+pub struct DeckStats {
+    pub cards: u32,
+    pub lands: u32,
+    pub creatures: u32,
+    /// Non-land cards by mana value; the last bucket is 7 or more.
+    pub curve: Vec<u32>,
+    /// Cards by type, most common first. A card with two types counts twice.
+    pub types: Vec<(String, u32)>,
+    /// Non-land cards by color. A multi-colored card counts once per color.
+    pub colors: Vec<(Color, u32)>,
+    /// Colored mana symbols in the mana costs.
+    pub pips: Mana,
+    total_mv: u32,
+}
+
+fn is_land(card: &Card) -> bool {
+    card.card_types.iter().any(|t| *t == "Land")
+}
+
+pub fn deck_stats(deck: &[(&Card, u32)]) -> DeckStats {
+    let mut stats = DeckStats {
+        cards: 0,
+        lands: 0,
+        creatures: 0,
+        curve: vec![0; CURVE_SIZE],
+        types: vec![],
+        colors: vec![],
+        pips: Mana::zero(),
+        total_mv: 0,
+    };
+
+    for &(card, n) in deck {
+        stats.cards += n;
+
+        for t in card.card_types.iter() {
+            match stats.types.iter_mut().find(|&&mut (ref s, _)| s == t) {
+                Some(e) => e.1 += n,
+                None => stats.types.push((t.clone(), n)),
+            }
+        }
+
+        if is_land(card) {
+            stats.lands += n;
+            continue;
+        }
+
+        if card.card_types.iter().any(|t| *t == "Creature") {
+            stats.creatures += n
+        }
+
+        let mv = card.mana_cost.cmc();
+        stats.curve[(mv as usize).min(CURVE_SIZE - 1)] += n;
+        stats.total_mv += mv * n;
+        let m = card.mana_cost;
+        stats.pips = stats.pips + Mana::new(m.w, m.u, m.b, m.r, m.g, 0, 0, 0) * n;
+
+        for &c in card.colors.iter() {
+            match stats.colors.iter_mut().find(|&&mut (k, _)| k == c) {
+                Some(e) => e.1 += n,
+                None => stats.colors.push((c, n)),
+            }
+        }
+    }
+
+    stats.types.sort_by(|a, b| b.1.cmp(&a.1));
+    stats.colors.sort();
+    stats
+}
+
+impl DeckStats {
+    pub fn spells(&self) -> u32 {
+        self.cards - self.lands
+    }
+
+    /// Average mana value over all cards, counting lands as 0.
+    pub fn average_mv(&self) -> f64 {
+        if self.cards == 0 { 0.0 } else { self.total_mv as f64 / self.cards as f64 }
+    }
+
+    /// Average mana value of the non-land cards.
+    pub fn average_mv_spells(&self) -> f64 {
+        if self.spells() == 0 { 0.0 } else { self.total_mv as f64 / self.spells() as f64 }
+    }
+
+    /// Lands per non-land card.
+    pub fn land_ratio(&self) -> f64 {
+        if self.spells() == 0 { 0.0 } else { self.lands as f64 / self.spells() as f64 }
+    }
+
+    pub fn summary_table(&self) -> Table {
+        let rows = vec![
+            ("Cards", format!("{}", self.cards)),
+            ("Lands", format!("{}", self.lands)),
+            ("Spells", format!("{}", self.spells())),
+            ("Creatures", format!("{}", self.creatures)),
+            ("Lands per spell", format!("{:.2}", self.land_ratio())),
+            ("Average MV", format!("{:.2}", self.average_mv())),
+            ("Average MV (spells)", format!("{:.2}", self.average_mv_spells())),
+            ("Color pips", self.pips.src()),
+        ];
+
+        let mut table = Table::new(rows.len(), 2);
+        for (r, (k, v)) in rows.into_iter().enumerate() {
+            table.set(r, 0, left(k));
+            table.set(r, 1, RStr(v));
+        }
+        table
+    }
+
+    pub fn curve_table(&self) -> Table {
+        let mut table = Table::new(2, 1 + CURVE_SIZE);
+        table.set(0, 0, left("MV"));
+        table.set(1, 0, left("#"));
+        for (mv, &n) in self.curve.iter().enumerate() {
+            let label = if mv == CURVE_SIZE - 1 { format!("{}+", mv) } else { mv.to_string() };
+            table.set(0, 1 + mv, RStr(label));
+            table.set(1, 1 + mv, U32(n));
+        }
+        table
+    }
+
+    pub fn types_table(&self) -> Table {
+        let mut table = Table::new(1 + self.types.len(), 2);
+        table.set(0, 0, left("Type"));
+        table.set(0, 1, right("#"));
+        for (r, &(ref t, n)) in self.types.iter().enumerate() {
+            table.set(1 + r, 0, LStr(t.clone()));
+            table.set(1 + r, 1, U32(n));
+        }
+        table
+    }
+
+    pub fn colors_table(&self) -> Table {
+        let mut table = Table::new(1 + self.colors.len(), 2);
+        table.set(0, 0, left("Color"));
+        table.set(0, 1, right("#"));
+        for (r, &(c, n)) in self.colors.iter().enumerate() {
+            table.set(1 + r, 0, LStr(format!("{:?}", c)));
+            table.set(1 + r, 1, U32(n));
+        }
+        table
+    }
+
+    /// The mana curve as horizontal bars, scaled so the longest is `width`.
+    pub fn curve_chart(&self, width: usize) -> String {
+        let max = self.curve.iter().cloned().max().unwrap_or(0).max(1) as usize;
+        let mut res = String::new();
+        for (mv, &n) in self.curve.iter().enumerate() {
+            let label = if mv == CURVE_SIZE - 1 { format!("{}+", mv) } else { mv.to_string() };
+            let len = (n as usize * width + max - 1) / max;
+            res.push_str(&format!("{:>2} | {} {}\n", label, repeat('#').take(len).collect::<String>(), n));
+        }
+        res
+    }
+
+    pub fn print(&self) {
+        self.summary_table().print("Deck");
+        self.curve_table().print("Mana curve");
+        self.types_table().print("Types");
+        self.colors_table().print("Colors");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mtgjson::Rarity;
+
+    fn card(name: &str, cost: &str, colors: &[Color], types: &[&str]) -> Card {
+        Card {
+            card_name: name.to_string(),
+            names: vec![],
+            mana_cost: Mana::parse(cost),
+            colors: colors.to_vec(),
+            card_type: types.join(" "),
+            super_types: vec![],
+            card_types: types.iter().map(|t| t.to_string()).collect(),
+            sub_types: vec![],
+            power: String::new(),
+            toughness: String::new(),
+            card_text: String::new(),
+            image_name: String::new(),
+            expansion: String::new(),
+            rarity: Rarity::Common,
+            legalities: vec![],
+        }
+    }
+
+    #[test]
+    fn small_deck() {
+        let island = card("Island", "", &[], &["Land"]);
+        let opt = card("Opt", "{U}", &[Color::U], &["Instant"]);
+        let bear = card("Bear", "{1}{G}", &[Color::G], &["Creature"]);
+        let wurm = card("Wurm", "{7}{G}{G}", &[Color::G], &["Artifact", "Creature"]);
+        let stats = deck_stats(&[(&island, 17), (&opt, 4), (&bear, 12), (&wurm, 2)]);
+
+        assert_eq!((stats.cards, stats.lands, stats.spells(), stats.creatures), (35, 17, 18, 14));
+        assert_eq!(stats.curve, vec![0, 4, 12, 0, 0, 0, 0, 2]);
+        let types: Vec<(&str, u32)> = stats.types.iter().map(|&(ref t, n)| (&**t, n)).collect();
+        assert_eq!(types, vec![("Land", 17), ("Creature", 14), ("Instant", 4), ("Artifact", 2)]);
+        assert_eq!(stats.colors, vec![(Color::U, 4), (Color::G, 14)]);
+        assert!((stats.average_mv_spells() - (4 + 24 + 18) as f64 / 18.0).abs() < 1e-9);
+
+        // The longest bar is 12 wide, and the others are rounded up.
+        let chart = stats.curve_chart(12);
+        assert_eq!(chart.lines().nth(2), Some(" 2 | ############ 12"));
+        assert_eq!(chart.lines().nth(1), Some(" 1 | #### 4"));
+        assert_eq!(chart.lines().nth(7), Some("7+ | ## 2"));
+        assert_eq!(chart.lines().nth(0), Some(" 0 |  0"));
+    }
+}